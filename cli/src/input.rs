@@ -0,0 +1,227 @@
+use mighty::prelude::{Card, Command, FriendFunc, Pattern, Rush};
+use std::str::FromStr;
+
+pub const HELP: &str = "\
+commands:
+  pledge <n> <s|d|h|c|n>      pledge n points with the giruda (n for no giruda)
+  pass                        pass the election
+  missed                      declare the missed deal
+  change <s|d|h|c|n>          change the giruda after the election
+  friend <friend> [drop <cards>]
+                              select the friend and drop cards, where <friend> is
+                              card <card>, user <n>, first, last or none
+  play <card> [<rush>] [call] place the card, with the pattern for the joker and joker call
+  random                      let the game choose for you
+  undo                        take back your last command
+  advice                      estimate the pledges you can make
+  accept, reject              answer to the request to take back the last command
+  start                       start the game
+  say <text>                  send chat
+  show                        show the game again
+  help                        show this help
+  quit                        quit
+cards are written like SA, D10, HQ, C2, JK-B and JK-R";
+
+/// Line typed by the user
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Input {
+    Command(Command),
+    Undo,
+    Advice,
+    Answer(bool),
+    Start,
+    Say(String),
+    Show,
+    Help,
+    Quit,
+}
+
+fn parse_giruda(s: &str) -> Result<Option<Pattern>, String> {
+    match s.to_lowercase().as_str() {
+        "n" | "no" | "none" => Ok(None),
+        _ => Pattern::from_str(s).map(Some).map_err(|e| e.to_string()),
+    }
+}
+
+fn parse_card(s: &str) -> Result<Card, String> {
+    Card::from_str(s).map_err(|e| e.to_string())
+}
+
+fn parse_friend(words: &[&str]) -> Result<Command, String> {
+    let (func, rest) = match words {
+        ["card", card, rest @ ..] => (FriendFunc::ByCard(parse_card(card)?), rest),
+        ["user", user, rest @ ..] => (
+            FriendFunc::ByUser(user.parse().map_err(|_| format!("invalid user: {}", user))?),
+            rest,
+        ),
+        ["first", rest @ ..] => (FriendFunc::First, rest),
+        ["last", rest @ ..] => (FriendFunc::Last, rest),
+        ["none", rest @ ..] => (FriendFunc::None, rest),
+        _ => return Err("usage: friend <card <card>|user <n>|first|last|none> [drop <cards>]".to_owned()),
+    };
+    let drop = match rest {
+        [] => Vec::new(),
+        ["drop", cards @ ..] => cards.iter().map(|c| parse_card(c)).collect::<Result<_, _>>()?,
+        _ => return Err(format!("expected drop, found {}", rest[0])),
+    };
+    Ok(Command::SelectFriend(drop, func))
+}
+
+fn parse_play(words: &[&str]) -> Result<Command, String> {
+    let (card, rest) = match words {
+        [card, rest @ ..] => (parse_card(card)?, rest),
+        _ => return Err("usage: play <card> [<rush>] [call]".to_owned()),
+    };
+    let (rest, joker_call) = match rest {
+        [rest @ .., "call"] => (rest, true),
+        _ => (rest, false),
+    };
+    let rush = match rest {
+        [] => Rush::from(card),
+        [rush] => Rush::from_str(rush).map_err(|e| e.to_string())?,
+        _ => return Err(format!("unexpected {}", rest[1])),
+    };
+    Ok(Command::Go(card, rush, joker_call))
+}
+
+/// Parses the line, see `HELP` for the syntax.
+pub fn parse(line: &str) -> Result<Input, String> {
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    let command = match words.as_slice() {
+        [] => return Err("type help for commands".to_owned()),
+        ["pledge", n, giruda] => Command::Pledge(Some((
+            parse_giruda(giruda)?,
+            n.parse().map_err(|_| format!("invalid pledge: {}", n))?,
+        ))),
+        ["pledge", ..] => return Err("usage: pledge <n> <s|d|h|c|n>".to_owned()),
+        ["pass"] => Command::Pledge(None),
+        ["missed"] => Command::DeclareMissedDeal,
+        ["change", giruda] => Command::ChangePledge(parse_giruda(giruda)?),
+        ["friend", rest @ ..] => parse_friend(rest)?,
+        ["play", rest @ ..] => parse_play(rest)?,
+        ["random"] => Command::Random,
+        ["undo"] => return Ok(Input::Undo),
+        ["advice"] => return Ok(Input::Advice),
+        ["accept"] => return Ok(Input::Answer(true)),
+        ["reject"] => return Ok(Input::Answer(false)),
+        ["start"] => return Ok(Input::Start),
+        ["say", ..] => return Ok(Input::Say(line.trim_start()[3..].trim().to_owned())),
+        ["show"] => return Ok(Input::Show),
+        ["help"] => return Ok(Input::Help),
+        ["quit"] | ["exit"] => return Ok(Input::Quit),
+        [x, ..] => return Err(format!("unknown command {}, type help for commands", x)),
+    };
+    Ok(Input::Command(command))
+}
+
+fn giruda_name(giruda: Option<Pattern>) -> String {
+    giruda.map_or("N".to_owned(), |p| p.to_string())
+}
+
+/// Command in the syntax of `parse`
+pub fn format(command: &Command) -> String {
+    match command {
+        Command::Pledge(Some((giruda, n))) => format!("pledge {} {}", n, giruda_name(*giruda)),
+        Command::Pledge(None) => "pass".to_owned(),
+        Command::DeclareMissedDeal => "missed".to_owned(),
+        Command::ChangePledge(giruda) => format!("change {}", giruda_name(*giruda)),
+        Command::SelectFriend(drop, func) => {
+            let func = match func {
+                FriendFunc::ByCard(card) => format!("card {}", card),
+                FriendFunc::ByUser(user) => format!("user {}", user),
+                FriendFunc::First => "first".to_owned(),
+                FriendFunc::Last => "last".to_owned(),
+                FriendFunc::None => "none".to_owned(),
+            };
+            if drop.is_empty() {
+                format!("friend {}", func)
+            } else {
+                let drop = drop.iter().map(Card::to_string).collect::<Vec<_>>();
+                format!("friend {} drop {}", func, drop.join(" "))
+            }
+        }
+        Command::Go(card, rush, joker_call) => {
+            let mut s = format!("play {}", card);
+            if *rush != Rush::from(*card) {
+                s += &format!(" {}", rush);
+            }
+            if *joker_call {
+                s += " call";
+            }
+            s
+        }
+        Command::Random => "random".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mighty::prelude::Color;
+
+    #[test]
+    fn parse_test() {
+        assert_eq!(
+            parse("pledge 14 s"),
+            Ok(Input::Command(Command::Pledge(Some((Some(Pattern::Spade), 14)))))
+        );
+        assert_eq!(
+            parse(" pledge 13  n"),
+            Ok(Input::Command(Command::Pledge(Some((None, 13)))))
+        );
+        assert_eq!(
+            parse("friend card SA drop D3 C4 H2"),
+            Ok(Input::Command(Command::SelectFriend(
+                Card::parse_hand("D3 C4 H2").unwrap(),
+                FriendFunc::ByCard(Card::Normal(Pattern::Spade, 14))
+            )))
+        );
+        assert_eq!(
+            parse("play HK"),
+            Ok(Input::Command(Command::Go(
+                Card::Normal(Pattern::Heart, 13),
+                Rush::from(Pattern::Heart),
+                false
+            )))
+        );
+        assert_eq!(
+            parse("play jk-r d"),
+            Ok(Input::Command(Command::Go(
+                Card::Joker(Color::Red),
+                Rush::from(Pattern::Diamond),
+                false
+            )))
+        );
+        assert_eq!(parse("say hello  world"), Ok(Input::Say("hello  world".to_owned())));
+        assert!(parse("pledge 14").is_err());
+        assert!(parse("play S1").is_err());
+        assert!(parse("friend card SA D3").is_err());
+        assert!(parse("").is_err());
+    }
+
+    #[test]
+    fn format_test() {
+        for line in [
+            "pledge 14 S",
+            "pledge 15 N",
+            "pass",
+            "missed",
+            "change D",
+            "friend card SA drop D3 C4 H2",
+            "friend user 2",
+            "friend first drop JK-B",
+            "play HK",
+            "play JK-R D",
+            "play C3 call",
+            "random",
+        ]
+        .iter()
+        {
+            let command = match parse(line) {
+                Ok(Input::Command(command)) => command,
+                x => panic!("{:?}", x),
+            };
+            assert_eq!(format(&command), *line);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use crate::input::{self, Input, HELP};
+use crate::render::{render, seat_name};
+use mighty::prelude::{next_user, Advice, Bot, Game, Rule};
+use std::io::{self, BufRead, Write};
+
+/// Sampled games for `advice`
+const ADVICE_SAMPLES: usize = 30;
+
+/// Local game, where every user is played by the bot or the human at this terminal
+pub struct Local {
+    game: Game,
+    // bot of each user, `None` for humans
+    bots: Vec<Option<Box<dyn Bot>>>,
+    // game before the last command of humans, with the commands of bots after it
+    undo: Option<Game>,
+}
+
+impl Local {
+    pub fn new(rule: Rule, bots: Vec<Option<Box<dyn Bot>>>) -> Local {
+        Local {
+            game: Game::new(rule),
+            bots,
+            undo: None,
+        }
+    }
+
+    fn user_cnt(&self) -> usize {
+        self.game.rule.user_cnt as usize
+    }
+
+    /// Hands of the user are shown, or every hand with `Table::open_hand`
+    fn view(&self, user: usize) -> Option<usize> {
+        Some(user).filter(|_| !self.game.rule.table.open_hand)
+    }
+
+    /// Plays games until the human quits.
+    pub fn run(&mut self) -> io::Result<()> {
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        println!("{}", HELP);
+        loop {
+            if self.game.is_finished() {
+                print!("{}", render(&self.game.state, self.user_cnt(), None));
+                loop {
+                    print!("start or quit> ");
+                    io::stdout().flush()?;
+                    match lines.next().transpose()?.map(|l| input::parse(&l)) {
+                        None | Some(Ok(Input::Quit)) => return Ok(()),
+                        Some(Ok(Input::Start)) => break,
+                        _ => {}
+                    }
+                }
+                self.game = Game::new(self.game.rule.clone());
+                self.undo = None;
+                continue;
+            }
+
+            let user = next_user(&self.game.rule, &self.game.state);
+            if let Some(bot) = &mut self.bots[user] {
+                let command = bot.command(&self.game.rule, &self.game.state, user);
+                let text = input::format(&command);
+                match self.game.next(user, command) {
+                    Ok(_) => println!("{}: {}", seat_name(user, self.user_cnt()), text),
+                    Err(e) => panic!("{} from {} in {:?}", e, bot.name(), self.game.state),
+                }
+                continue;
+            }
+
+            // hands are shown again for the next human when they take turns on the same terminal
+            print!("{}", render(&self.game.state, self.user_cnt(), self.view(user)));
+            print!("{}> ", seat_name(user, self.user_cnt()));
+            io::stdout().flush()?;
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => return Ok(()),
+            };
+            match input::parse(&line) {
+                Ok(Input::Command(command)) => {
+                    let before = self.game.clone();
+                    match self.game.next(user, command) {
+                        Ok(_) => self.undo = Some(before),
+                        Err(e) => println!("error: {}", e),
+                    }
+                }
+                Ok(Input::Undo) => match self.undo.take() {
+                    Some(game) => {
+                        self.game = game;
+                        println!("took back the last command");
+                    }
+                    None => println!("error: nothing to take back"),
+                },
+                Ok(Input::Advice) => self.advice(user),
+                Ok(Input::Show) => {}
+                Ok(Input::Help) => println!("{}", HELP),
+                Ok(Input::Quit) => return Ok(()),
+                Ok(_) => println!("error: not available in the local game"),
+                Err(e) => println!("error: {}", e),
+            }
+        }
+    }
+
+    fn advice(&self, user: usize) {
+        match Advice::estimate(&self.game.rule, &self.game.state, user, ADVICE_SAMPLES) {
+            Ok(advice) => print!("{}", crate::advice(&advice)),
+            Err(e) => println!("error: {}", e),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! Plays Mighty in the terminal.
+//!
+//! ```text
+//! mighty-cli [--preset Default5] [--bot heuristic|random] [--hot-seat]
+//! mighty-cli connect <host:port> --user <user no> (--cookie <cookie> | --login <user id>)
+//! ```
+//!
+//! The local game is played by the user 0 against bots, or by every user at this terminal with `--hot-seat`.
+//! With `connect`, the user plays in the room of the server which the user already joined.
+
+mod input;
+mod local;
+mod remote;
+mod render;
+
+use local::Local;
+use mighty::prelude::{Advice, Bot, HeuristicBot, Preset, RandomBot, Rule};
+use remote::Remote;
+use std::io::{self, Write};
+use std::process::exit;
+use types::UserNo;
+
+const USAGE: &str = "usage: mighty-cli [--preset <preset>] [--bot heuristic|random] [--hot-seat]
+       mighty-cli connect <host:port> --user <user no> (--cookie <cookie> | --login <user id>)";
+
+/// Estimated results of the pledges, one line for each pledge
+pub fn advice(advice: &[Advice]) -> String {
+    advice
+        .iter()
+        .map(|a| {
+            let giruda = a.giruda.map_or("N".to_owned(), |p| p.to_string());
+            format!(
+                "  pledge {} {}: win {:.0}%, score {:+.1}\n",
+                a.pledge,
+                giruda,
+                a.win_rate() * 100.0,
+                a.mean()
+            )
+        })
+        .collect()
+}
+
+fn bot(name: &str) -> Result<Box<dyn Bot>, String> {
+    match name {
+        "heuristic" => Ok(Box::new(HeuristicBot)),
+        "random" => Ok(Box::new(RandomBot)),
+        _ => Err(format!("unknown bot {}", name)),
+    }
+}
+
+fn local(args: &[String]) -> Result<(), String> {
+    let mut preset = Preset::Default5;
+    let mut bot_name = "heuristic".to_owned();
+    let mut hot_seat = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value of {}", arg));
+        match arg.as_str() {
+            "--preset" => {
+                let name = value()?;
+                preset = serde_json::from_value(name.clone().into()).map_err(|_| format!("unknown preset {}", name))?;
+            }
+            "--bot" => bot_name = value()?.clone(),
+            "--hot-seat" => hot_seat = true,
+            x => return Err(format!("unknown argument {}", x)),
+        }
+    }
+    let rule = Rule::from(preset);
+    let bots = (0..rule.user_cnt)
+        .map(|user| {
+            if user == 0 || hot_seat {
+                Ok(None)
+            } else {
+                bot(&bot_name).map(Some)
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Local::new(rule, bots).run().map_err(|e| e.to_string())
+}
+
+fn connect(args: &[String]) -> Result<(), String> {
+    let mut args = args.iter();
+    let addr = args.next().ok_or("missing address")?;
+    let addr = addr.trim_start_matches("http://").trim_start_matches("ws://");
+    let (mut user, mut cookie, mut login) = (None, None, None);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value of {}", arg));
+        match arg.as_str() {
+            "--user" => user = Some(UserNo(value()?.parse().map_err(|_| "invalid user no".to_owned())?)),
+            "--cookie" => cookie = Some(value()?.clone()),
+            "--login" => login = Some(value()?.clone()),
+            x => return Err(format!("unknown argument {}", x)),
+        }
+    }
+    let user = user.ok_or("missing --user")?;
+    let cookie = match (cookie, login) {
+        (Some(cookie), _) => cookie,
+        (None, Some(login)) => {
+            print!("password: ");
+            io::stdout().flush().map_err(|e| e.to_string())?;
+            // the password is not echoed
+            let password = rpassword::read_password().map_err(|e| e.to_string())?;
+            remote::login(addr, &login, &password).map_err(|e| e.to_string())?
+        }
+        (None, None) => return Err("missing --cookie or --login".to_owned()),
+    };
+    let mut remote = Remote::connect(addr, &cookie, user).map_err(|e| e.to_string())?;
+    remote.run().map_err(|e| e.to_string())
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let res = match args.first().map(String::as_str) {
+        Some("-h") | Some("--help") => Err(String::new()),
+        Some("connect") => connect(&args[1..]),
+        _ => local(&args),
+    };
+    if let Err(e) = res {
+        if !e.is_empty() {
+            eprintln!("{}", e);
+        }
+        eprintln!("{}", USAGE);
+        exit(2);
+    }
+}
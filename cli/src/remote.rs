@@ -0,0 +1,280 @@
+//! Plays in the room of a running server, over HTTP and websocket.
+//!
+//! The user should be already joined to the room, and the session cookie is taken from `/login`.
+//! TLS is not supported, so the server should be reachable without https redirection.
+
+use crate::input::{self, Input, HELP};
+use crate::render::render;
+use mighty::prelude::{Format, Rule, State};
+use std::io::{self, BufRead, BufReader};
+use std::net::TcpStream;
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+use std::time::Duration;
+use tungstenite::client::IntoClientRequest;
+use tungstenite::protocol::WebSocketConfig;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+use types::{ChatChannel, RoomInfo, RoomUserToClient, RoomUserToServer, RuleHash, UserNo};
+
+/// Name of the session cookie of the server
+const COOKIE: &str = "web-mighty-auth";
+
+/// Messages of the server are small, so a longer frame or message is taken as a broken connection.
+const MAX_MESSAGE_SIZE: usize = 1 << 20;
+
+/// Interval to check the input of the user while waiting for the server
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+fn invalid<E: ToString>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+/// Logs in and returns the session cookie.
+pub fn login(addr: &str, user_id: &str, password: &str) -> io::Result<String> {
+    let body = serde_json::json!({ "user_id": user_id, "password": password }).to_string();
+    let response = ureq::post(&format!("http://{}/login", addr))
+        .set("Content-Type", "application/json")
+        .send_string(&body)
+        .map_err(|e| match e {
+            ureq::Error::Status(status, _) => invalid(format!("login failed with status {}", status)),
+            e => invalid(e),
+        })?;
+    response
+        .all("set-cookie")
+        .into_iter()
+        .filter_map(|v| v.split(';').next()?.trim().strip_prefix(COOKIE)?.strip_prefix('='))
+        .map(str::to_owned)
+        .next()
+        .ok_or_else(|| invalid("no session cookie"))
+}
+
+fn get_rule(addr: &str, hash: &RuleHash) -> io::Result<Rule> {
+    let body = ureq::get(&format!("http://{}/rule/{}?format=toml", addr, hash))
+        .call()
+        .map_err(|_| invalid(format!("no rule {}", hash)))?
+        .into_string()?;
+    Rule::import(&body, Format::Toml).map_err(invalid)
+}
+
+/// Room connection of the user
+pub struct Remote {
+    addr: String,
+    me: UserNo,
+    socket: WebSocket<MaybeTlsStream<TcpStream>>,
+    info: Option<RoomInfo>,
+    rule: Option<Rule>,
+    state: Option<State>,
+}
+
+impl Remote {
+    pub fn connect(addr: &str, cookie: &str, me: UserNo) -> io::Result<Remote> {
+        let mut request = format!("ws://{}/ws/room", addr)
+            .into_client_request()
+            .map_err(invalid)?;
+        let cookie = format!("{}={}", COOKIE, cookie).parse().map_err(invalid)?;
+        request.headers_mut().insert("Cookie", cookie);
+        let config = WebSocketConfig {
+            max_message_size: Some(MAX_MESSAGE_SIZE),
+            max_frame_size: Some(MAX_MESSAGE_SIZE),
+            ..WebSocketConfig::default()
+        };
+        let (socket, _) = tungstenite::client::connect_with_config(request, Some(config), 0).map_err(invalid)?;
+        // reading times out, so that the input of the user is handled while the server is quiet
+        if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+            stream.set_read_timeout(Some(POLL_INTERVAL))?;
+        }
+        Ok(Remote {
+            addr: addr.to_owned(),
+            me,
+            socket,
+            info: None,
+            rule: None,
+            state: None,
+        })
+    }
+
+    fn send(&mut self, msg: RoomUserToServer) -> io::Result<()> {
+        let text = serde_json::to_string(&msg).map_err(invalid)?;
+        self.socket.send(Message::Text(text)).map_err(invalid)
+    }
+
+    fn show(&self) {
+        let (info, state) = match (&self.info, &self.state) {
+            (Some(info), Some(state)) => (info, state),
+            _ => return,
+        };
+        let open_hand = matches!(&self.rule, Some(r) if r.table.open_hand);
+        let view = info.user.iter().position(|u| *u == self.me).filter(|_| !open_hand);
+        print!("{}", render(state, info.user.len(), view));
+    }
+
+    fn receive(&mut self, msg: RoomUserToClient) {
+        match msg {
+            RoomUserToClient::Room(info) => {
+                let users = info.user.iter().map(|u| u.to_string()).collect::<Vec<_>>();
+                println!(
+                    "room {} '{}', users {}, head {}{}",
+                    info.id,
+                    info.name,
+                    users.join(" "),
+                    info.head,
+                    if info.is_game { ", in game" } else { "" }
+                );
+                if !matches!(&self.info, Some(i) if i.rule == info.rule) {
+                    self.rule = get_rule(&self.addr, &info.rule)
+                        .map_err(|e| println!("error: {}", e))
+                        .ok();
+                }
+                self.info = Some(info);
+            }
+            RoomUserToClient::Game(state) => {
+                self.state = Some(state);
+                self.show();
+            }
+            RoomUserToClient::Chat(chat) => println!("[{}] {}", chat.user, chat.content),
+            RoomUserToClient::DeleteChat(_) => {}
+            RoomUserToClient::Error { code, message } => println!("error: {} ({})", message, code),
+            RoomUserToClient::InvalidRule(errors) => {
+                for e in errors {
+                    println!("invalid rule: {} ({})", e.message, e.code);
+                }
+            }
+            RoomUserToClient::UndoRequest(Some(user)) => {
+                println!("user {} requests to take back the last command, accept or reject", user)
+            }
+            RoomUserToClient::UndoRequest(None) => println!("the request to take back is closed"),
+            RoomUserToClient::Advice(advice) => print!("{}", crate::advice(&advice)),
+        }
+    }
+
+    /// Handles the line of the user, and returns `false` if the user quits.
+    fn input(&mut self, line: &str) -> io::Result<bool> {
+        let msg = match input::parse(line) {
+            Ok(Input::Command(command)) => RoomUserToServer::Command(command),
+            Ok(Input::Undo) => RoomUserToServer::RequestUndo,
+            Ok(Input::Advice) => RoomUserToServer::RequestAdvice,
+            Ok(Input::Answer(accept)) => RoomUserToServer::AnswerUndo(accept),
+            Ok(Input::Start) => RoomUserToServer::Start,
+            Ok(Input::Say(text)) => RoomUserToServer::Chat(ChatChannel::Room, text),
+            Ok(Input::Show) => {
+                self.show();
+                return Ok(true);
+            }
+            Ok(Input::Help) => {
+                println!("{}", HELP);
+                return Ok(true);
+            }
+            Ok(Input::Quit) => {
+                let _ = self.socket.close(None);
+                let _ = self.socket.flush();
+                return Ok(false);
+            }
+            Err(e) => {
+                println!("error: {}", e);
+                return Ok(true);
+            }
+        };
+        self.send(msg)?;
+        Ok(true)
+    }
+
+    /// Plays until the human quits or the server closes the connection.
+    pub fn run(&mut self) -> io::Result<()> {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(io::stdin()).lines() {
+                match line {
+                    Ok(line) => {
+                        if tx.send(line).is_err() {
+                            return;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+            let _ = tx.send("quit".to_owned());
+        });
+
+        println!("{}", HELP);
+        loop {
+            // pings of the server are answered by the socket while reading
+            match self.socket.read() {
+                Ok(Message::Text(text)) => {
+                    if let Ok(msg) = serde_json::from_str(&text) {
+                        self.receive(msg);
+                    }
+                }
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e))
+                    if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {}
+                Err(tungstenite::Error::ConnectionClosed) | Err(tungstenite::Error::AlreadyClosed) => {
+                    println!("connection is closed");
+                    return Ok(());
+                }
+                Err(e) => return Err(invalid(e)),
+            }
+            loop {
+                match rx.try_recv() {
+                    Ok(line) => {
+                        if !self.input(&line)? {
+                            return Ok(());
+                        }
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return Ok(()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::TcpListener;
+    use tungstenite::handshake::server::{Request, Response};
+
+    // error of the handshake callback is the response of tungstenite
+    #[allow(clippy::result_large_err)]
+    #[test]
+    fn connect_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let check_cookie = |req: &Request, res: Response| {
+                assert_eq!(req.headers()["cookie"], format!("{}=abc", COOKIE).as_str());
+                Ok(res)
+            };
+            let mut socket = tungstenite::accept_hdr(stream, check_cookie).unwrap();
+            let msg = RoomUserToClient::DeleteChat(3);
+            socket
+                .send(Message::Text(serde_json::to_string(&msg).unwrap()))
+                .unwrap();
+            let _ = socket.send(Message::Text("x".repeat(MAX_MESSAGE_SIZE + 1)));
+        });
+
+        let mut remote = Remote::connect(&addr, "abc", UserNo(1)).unwrap();
+        let msg = loop {
+            match remote.socket.read() {
+                Ok(Message::Text(text)) => break text,
+                Err(tungstenite::Error::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                x => panic!("{:?}", x),
+            }
+        };
+        assert!(matches!(
+            serde_json::from_str(&msg).unwrap(),
+            RoomUserToClient::DeleteChat(3)
+        ));
+        // too long message is taken as a broken connection
+        let res = loop {
+            match remote.socket.read() {
+                Err(tungstenite::Error::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                res => break res,
+            }
+        };
+        assert!(matches!(res, Err(tungstenite::Error::Capacity(_))));
+        server.join().unwrap();
+    }
+}
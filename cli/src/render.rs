@@ -0,0 +1,210 @@
+use mighty::prelude::{Card, FriendFunc, Pattern, State};
+use std::fmt::Write;
+
+/// Name of the seat, where seats after the users are dummy hands
+pub fn seat_name(seat: usize, user_cnt: usize) -> String {
+    if seat < user_cnt {
+        format!("user {}", seat)
+    } else {
+        format!("dummy {}", seat)
+    }
+}
+
+/// Cards in notation, sorted by pattern and number
+pub fn cards(cards: &[Card]) -> String {
+    let mut cards = cards.to_vec();
+    cards.sort();
+    let cards = cards.iter().map(Card::to_string).collect::<Vec<_>>();
+    if cards.is_empty() {
+        "-".to_owned()
+    } else {
+        cards.join(" ")
+    }
+}
+
+fn giruda(giruda: Option<Pattern>) -> String {
+    giruda.map_or("no giruda".to_owned(), |p| p.to_string())
+}
+
+fn friend(func: &FriendFunc, friend: Option<usize>, is_friend_known: bool, user_cnt: usize) -> String {
+    match (friend, is_friend_known) {
+        (Some(friend), true) => seat_name(friend, user_cnt),
+        _ => match func {
+            FriendFunc::None => "none".to_owned(),
+            FriendFunc::ByCard(card) => format!("the owner of {}", card),
+            FriendFunc::ByUser(user) => seat_name(*user, user_cnt),
+            FriendFunc::First => "the winner of the first trick".to_owned(),
+            FriendFunc::Last => "the winner of the last trick".to_owned(),
+        },
+    }
+}
+
+/// Hands of the seats which can be seen from `view`, which sees every hand if `None`.
+/// Dummy hands are seen by everyone.
+fn hands(out: &mut String, deck: &[Vec<Card>], user_cnt: usize, view: Option<usize>) {
+    for (seat, hand) in deck.iter().enumerate() {
+        if !matches!(view, Some(v) if v != seat) || seat >= user_cnt {
+            let _ = writeln!(out, "  {:<9} {}", seat_name(seat, user_cnt), cards(hand));
+        }
+    }
+}
+
+/// The game from the view of the user, `None` to see every hand
+pub fn render(state: &State, user_cnt: usize, view: Option<usize>) -> String {
+    let mut out = String::new();
+    match state {
+        State::Election {
+            pledge,
+            done,
+            curr_user,
+            deck,
+            revealed,
+            ..
+        } => {
+            let _ = writeln!(out, "== election, turn of {}", seat_name(*curr_user, user_cnt));
+            if let Some((user, hand)) = revealed {
+                let _ = writeln!(out, "  missed deal of {}: {}", seat_name(*user, user_cnt), cards(hand));
+            }
+            for (user, (pledge, done)) in pledge.iter().zip(done.iter()).enumerate() {
+                let status = match (pledge, done) {
+                    (_, true) => "pass".to_owned(),
+                    (Some((g, n)), false) => format!("{} {}", n, giruda(*g)),
+                    (None, false) => "-".to_owned(),
+                };
+                let _ = writeln!(out, "  {:<9} {}", seat_name(user, user_cnt), status);
+            }
+            hands(&mut out, deck, user_cnt, view);
+        }
+        State::SelectFriend {
+            president,
+            giruda: g,
+            pledge,
+            deck,
+            kitty,
+        } => {
+            let _ = writeln!(
+                out,
+                "== {} is the president with {} {}, selecting the friend",
+                seat_name(*president, user_cnt),
+                pledge,
+                giruda(*g)
+            );
+            if !matches!(view, Some(v) if v != *president) && !kitty.is_empty() {
+                let _ = writeln!(out, "  kitty     {}", cards(kitty));
+            }
+            hands(&mut out, deck, user_cnt, view);
+        }
+        State::InGame {
+            president,
+            friend_func,
+            friend: f,
+            is_friend_known,
+            giruda: g,
+            pledge,
+            deck,
+            score_deck,
+            tricks,
+            turn_count,
+            placed_cards,
+            start_user,
+            current_user,
+            ..
+        } => {
+            let _ = writeln!(
+                out,
+                "== trick {}, {} {} by {}, friend is {}",
+                turn_count + 1,
+                pledge,
+                giruda(*g),
+                seat_name(*president, user_cnt),
+                friend(friend_func, *f, *is_friend_known, user_cnt)
+            );
+            if let Some(trick) = tricks.last() {
+                let placed = trick
+                    .cards
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (card, _))| format!("{} {}", seat_name((trick.leader + i) % deck.len(), user_cnt), card))
+                    .collect::<Vec<_>>();
+                let _ = writeln!(
+                    out,
+                    "  last trick: {}, taken by {}",
+                    placed.join(", "),
+                    seat_name(trick.winner, user_cnt)
+                );
+            }
+            let points = score_deck
+                .iter()
+                .enumerate()
+                .filter(|(_, d)| !d.is_empty())
+                .map(|(seat, d)| format!("{} {}", seat_name(seat, user_cnt), d.len()))
+                .collect::<Vec<_>>();
+            if !points.is_empty() {
+                let _ = writeln!(out, "  points: {}", points.join(", "));
+            }
+            let mut seat = *start_user;
+            let mut placed = Vec::new();
+            while seat != *current_user {
+                placed.push(format!("{} {}", seat_name(seat, user_cnt), placed_cards[seat].0));
+                seat = (seat + 1) % deck.len();
+            }
+            if !placed.is_empty() {
+                let _ = writeln!(out, "  on table: {}", placed.join(", "));
+            }
+            let _ = writeln!(out, "  turn of {}", seat_name(*current_user, user_cnt));
+            hands(&mut out, deck, user_cnt, view);
+        }
+        State::GameEnded {
+            winner,
+            president,
+            friend,
+            scores,
+            pledge,
+            giruda: g,
+            points,
+            ..
+        } => {
+            let is_win = winner & 1 << president != 0;
+            let _ = writeln!(
+                out,
+                "== game ended, the ruling party {} with {} points for {} {}",
+                if is_win { "won" } else { "lost" },
+                points,
+                pledge,
+                giruda(*g)
+            );
+            let _ = writeln!(
+                out,
+                "  president {}, friend {}",
+                seat_name(*president, user_cnt),
+                friend.map_or("none".to_owned(), |f| seat_name(f, user_cnt))
+            );
+            for (user, score) in scores.iter().enumerate().take(user_cnt) {
+                let _ = writeln!(out, "  {:<9} {:+}", seat_name(user, user_cnt), score);
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mighty::prelude::{Preset, Rule};
+
+    #[test]
+    fn render_test() {
+        let rule = Rule::from(Preset::Default5);
+        let state = State::new(&rule);
+        let hand = match &state {
+            State::Election { deck, .. } => cards(&deck[2]),
+            _ => unreachable!(),
+        };
+        let out = render(&state, 5, Some(2));
+        assert!(out.starts_with("== election"));
+        assert!(out.contains(&hand));
+        assert_eq!(out.lines().filter(|l| l.contains(&hand)).count(), 1);
+        assert_eq!(render(&state, 5, None).lines().count(), 11);
+        assert_eq!(cards(&Card::parse_hand("SA S2 D10").unwrap()), "S2 SA D10");
+    }
+}
@@ -25,6 +25,8 @@ impl SessionTrait for MainSession {
                 ("user_status", JsValue::from_serde(&Status { no, status }).unwrap())
             }
             MainToClient::UserInfo(info) => ("user_info", JsValue::from_serde(&info).unwrap()),
+            MainToClient::DirectMessage(msg) => ("direct_message", JsValue::from_serde(&msg).unwrap()),
+            MainToClient::Error { code, message } => ("error", JsValue::from_serde(&(code, message)).unwrap()),
         }
     }
 }
@@ -58,4 +60,8 @@ impl Main {
     pub fn unsubscribe(&self, user_no: UserNo) {
         self.session.send(MainToServer::Unsubscribe(user_no));
     }
+
+    pub fn direct_message(&self, to: UserNo, content: String) {
+        self.session.send(MainToServer::DirectMessage(to, content));
+    }
 }
@@ -16,7 +16,9 @@ impl SessionTrait for ObserveSession {
         match msg {
             ObserveToClient::Room(info) => ("room_info", JsValue::from_serde(&info).unwrap()),
             ObserveToClient::Game(state) => ("game_state", JsValue::from_serde(&state).unwrap()),
-            ObserveToClient::Chat(chat, no) => ("chat", JsValue::from_serde(&(chat, no)).unwrap()),
+            ObserveToClient::Chat(chat) => ("chat", JsValue::from_serde(&chat).unwrap()),
+            ObserveToClient::DeleteChat(id) => ("delete_chat", JsValue::from_serde(&id).unwrap()),
+            ObserveToClient::Error { code, message } => ("error", JsValue::from_serde(&(code, message)).unwrap()),
         }
     }
 }
@@ -38,4 +40,13 @@ impl Observe {
     pub fn on(&self, tag: String, callback: Function) {
         self.session.on(tag, callback);
     }
+
+    pub fn chat(&self, channel: &JsValue, chat: String) {
+        self.session
+            .send(ObserveToServer::Chat(channel.into_serde().unwrap(), chat));
+    }
+
+    pub fn delete_chat(&self, id: u64) {
+        self.session.send(ObserveToServer::DeleteChat(id));
+    }
 }
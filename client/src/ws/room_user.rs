@@ -1,6 +1,7 @@
 use crate::prelude::*;
 use crate::ws::session::{Context, Session, SessionTrait};
-use types::{RoomUserToClient, RoomUserToServer};
+use mighty::prelude::Rule;
+use types::{RoomUserToClient, RoomUserToServer, RuleViolation, UserNo};
 
 pub struct UserSession;
 
@@ -16,7 +17,12 @@ impl SessionTrait for UserSession {
         match msg {
             RoomUserToClient::Room(info) => ("room_info", JsValue::from_serde(&info).unwrap()),
             RoomUserToClient::Game(state) => ("game_state", JsValue::from_serde(&state).unwrap()),
-            RoomUserToClient::Chat(chat, no) => ("chat", JsValue::from_serde(&(chat, no)).unwrap()),
+            RoomUserToClient::Chat(chat) => ("chat", JsValue::from_serde(&chat).unwrap()),
+            RoomUserToClient::DeleteChat(id) => ("delete_chat", JsValue::from_serde(&id).unwrap()),
+            RoomUserToClient::Error { code, message } => ("error", JsValue::from_serde(&(code, message)).unwrap()),
+            RoomUserToClient::InvalidRule(errors) => ("invalid_rule", JsValue::from_serde(&errors).unwrap()),
+            RoomUserToClient::UndoRequest(user) => ("undo_request", JsValue::from_serde(&user).unwrap()),
+            RoomUserToClient::Advice(advice) => ("advice", JsValue::from_serde(&advice).unwrap()),
         }
     }
 }
@@ -52,7 +58,56 @@ impl User {
             .send(RoomUserToServer::ChangeRule(rule.into_serde().unwrap()))
     }
 
+    /// Returns the violated constraints of the rule, which is empty if the rule is valid.
+    /// Server rejects the rule with the same constraints by `invalid_rule`.
+    pub fn validate_rule(&self, rule: &JsValue) -> JsValue {
+        let rule: Rule = rule.into_serde().unwrap();
+        let errors = match rule.validate() {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.iter().map(RuleViolation::from).collect(),
+        };
+        JsValue::from_serde(&errors).unwrap()
+    }
+
+    pub fn change_spectate_mode(&self, mode: &JsValue) {
+        self.session
+            .send(RoomUserToServer::ChangeSpectateMode(mode.into_serde().unwrap()))
+    }
+
+    pub fn change_allow_undo(&self, allow: bool) {
+        self.session.send(RoomUserToServer::ChangeAllowUndo(allow));
+    }
+
     pub fn command(&self, cmd: &JsValue) {
         self.session.send(RoomUserToServer::Command(cmd.into_serde().unwrap()))
     }
+
+    pub fn request_undo(&self) {
+        self.session.send(RoomUserToServer::RequestUndo);
+    }
+
+    pub fn answer_undo(&self, accept: bool) {
+        self.session.send(RoomUserToServer::AnswerUndo(accept));
+    }
+
+    pub fn request_advice(&self) {
+        self.session.send(RoomUserToServer::RequestAdvice);
+    }
+
+    pub fn chat(&self, channel: &JsValue, chat: String) {
+        self.session
+            .send(RoomUserToServer::Chat(channel.into_serde().unwrap(), chat));
+    }
+
+    pub fn mute(&self, user_no: UserNo) {
+        self.session.send(RoomUserToServer::Mute(user_no));
+    }
+
+    pub fn unmute(&self, user_no: UserNo) {
+        self.session.send(RoomUserToServer::Unmute(user_no));
+    }
+
+    pub fn delete_chat(&self, id: u64) {
+        self.session.send(RoomUserToServer::DeleteChat(id));
+    }
 }
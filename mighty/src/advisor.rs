@@ -0,0 +1,199 @@
+use crate::card::Pattern;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "server")]
+use {
+    crate::bot::{next_user, Bot, HeuristicBot},
+    crate::command::Command,
+    crate::error::{Error, Result},
+    crate::rule::{kitty::KittyPoints, Rule},
+    crate::state::{FriendFunc, State},
+    rand::seq::SliceRandom,
+};
+
+/// Estimated result of pledging with the giruda, from the view of a user in the election
+///
+/// - `scores`: score of the user as the president in each sampled game, by `Rule::scoring`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Advice {
+    pub giruda: Option<Pattern>,
+    pub pledge: u8,
+    pub scores: Vec<i32>,
+}
+
+impl Advice {
+    /// Average score of the sampled games
+    pub fn mean(&self) -> f64 {
+        if self.scores.is_empty() {
+            return 0.0;
+        }
+        self.scores.iter().sum::<i32>() as f64 / self.scores.len() as f64
+    }
+
+    /// Ratio of the sampled games which the ruling party won
+    pub fn win_rate(&self) -> f64 {
+        if self.scores.is_empty() {
+            return 0.0;
+        }
+        self.scores.iter().filter(|s| **s > 0).count() as f64 / self.scores.len() as f64
+    }
+
+    /// Estimates every pledge which the user can make now, or as if it is the user's turn.
+    ///
+    /// Hands which the user can't see are dealt again from the unseen cards `samples` times,
+    /// and every sampled game is played by `HeuristicBot` with the user as the president.
+    /// One game is played for each giruda, and it is scored by every pledge of the giruda.
+    #[cfg(feature = "server")]
+    pub fn estimate(rule: &Rule, state: &State, user_id: usize, samples: usize) -> Result<Vec<Advice>> {
+        let (deck, left) = match state {
+            State::Election { deck, left, .. } => (deck, left),
+            _ => return Err(Error::InvalidCommand(state.phase())),
+        };
+        if user_id >= rule.user_cnt as usize {
+            return Err(Error::NotPlayer);
+        }
+        let mut turn = state.clone();
+        if let State::Election { curr_user, .. } = &mut turn {
+            *curr_user = user_id;
+        }
+        let mut advice = turn
+            .legal_commands(user_id, rule)
+            .into_iter()
+            .filter_map(|cmd| match cmd {
+                Command::Pledge(Some((giruda, pledge))) => Some(Advice {
+                    giruda,
+                    pledge,
+                    scores: Vec::new(),
+                }),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let mut girudas = advice.iter().map(|a| (a.giruda, a.pledge)).collect::<Vec<_>>();
+        girudas.dedup_by_key(|(giruda, _)| *giruda);
+
+        let hidden = if rule.table.open_hand {
+            0
+        } else {
+            rule.user_cnt as usize
+        };
+        let mut rng = rand::thread_rng();
+        for _ in 0..samples {
+            let mut unseen = deck
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i < hidden && *i != user_id)
+                .flat_map(|(_, d)| d.iter().copied())
+                .chain(left.iter().copied())
+                .collect::<Vec<_>>();
+            unseen.shuffle(&mut rng);
+            let mut sampled = deck.clone();
+            for (_, hand) in sampled
+                .iter_mut()
+                .enumerate()
+                .filter(|(i, _)| *i < hidden && *i != user_id)
+            {
+                let cnt = hand.len();
+                *hand = unseen.split_off(unseen.len() - cnt);
+            }
+            sampled[user_id].extend(unseen.iter().copied());
+
+            for (giruda, pledge) in girudas.iter() {
+                let start = State::SelectFriend {
+                    president: user_id,
+                    giruda: *giruda,
+                    pledge: *pledge,
+                    deck: sampled.clone(),
+                    kitty: unseen.clone(),
+                };
+                let (points, run, no_friend) = Advice::play_out(rule, start)?;
+                for a in advice.iter_mut().filter(|a| a.giruda == *giruda) {
+                    let (is_win, score, _) =
+                        rule.scoring
+                            .game_score(a.pledge, points, run, giruda.is_none(), no_friend);
+                    let sign = if is_win { 1 } else { -1 };
+                    a.scores
+                        .push(sign * score * rule.scoring.share_of(user_id, user_id, None));
+                }
+            }
+        }
+        Ok(advice)
+    }
+
+    /// Plays the game to the end with the bot, and returns the points of the ruling party,
+    /// if the opposition took no point, and if the president played without friend.
+    #[cfg(feature = "server")]
+    fn play_out(rule: &Rule, mut state: State) -> Result<(u8, bool, bool)> {
+        let mut bot = HeuristicBot;
+        let mut no_friend = false;
+        loop {
+            match &state {
+                State::InGame { friend_func, .. } => no_friend = *friend_func == FriendFunc::None,
+                State::GameEnded {
+                    president,
+                    friend,
+                    score_deck,
+                    dropped,
+                    points,
+                    ..
+                } => {
+                    let is_ruling = |i: usize| i == *president || *friend == Some(i);
+                    let dropped_points = match rule.kitty.points {
+                        KittyPoints::Opposition => dropped.iter().filter(|c| c.is_score()).count(),
+                        _ => 0,
+                    };
+                    let opposition_points = score_deck
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| !is_ruling(*i))
+                        .map(|(_, d)| d.len())
+                        .sum::<usize>()
+                        + dropped_points;
+                    return Ok((*points, opposition_points == 0, no_friend));
+                }
+                _ => {}
+            }
+            let user = next_user(rule, &state);
+            let cmd = bot.command(rule, &state, user);
+            state = state.next(user, cmd, rule)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "server")]
+    use {super::*, crate::rule::Preset};
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn estimate_test() {
+        let rule = Rule::from(Preset::Default5);
+        let state = State::new(&rule);
+        let advice = Advice::estimate(&rule, &state, 2, 3).unwrap();
+        for a in advice.iter() {
+            assert!(advice
+                .iter()
+                .any(|b| b.giruda == a.giruda && b.pledge == rule.pledge.max));
+            assert_eq!(a.scores.len(), 3);
+            assert!(a.pledge >= rule.pledge.min);
+            let win_rate = a.win_rate();
+            assert!((0.0..=1.0).contains(&win_rate));
+        }
+        // higher pledge with same giruda is the same game with higher stake
+        for a in advice.iter() {
+            for b in advice.iter().filter(|b| b.giruda == a.giruda && b.pledge > a.pledge) {
+                assert!(b.win_rate() <= a.win_rate());
+            }
+        }
+
+        assert_eq!(Advice::estimate(&rule, &state, 5, 1), Err(Error::NotPlayer));
+        let mut state = state;
+        while let State::Election { .. } = state {
+            let user = state.valid_users(&rule).trailing_zeros() as usize;
+            state = state.next(user, Command::Random, &rule).unwrap();
+        }
+        assert_eq!(
+            Advice::estimate(&rule, &state, 0, 1),
+            Err(Error::InvalidCommand(state.phase()))
+        );
+    }
+}
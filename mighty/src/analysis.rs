@@ -0,0 +1,317 @@
+use crate::card::{Card, Pattern, Rush};
+use crate::command::Command;
+use crate::error::{Error, Result};
+use crate::rule::{card_policy::CardPolicy, Rule};
+use crate::state::{FriendFunc, State};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Placed card of the reviewed game
+///
+/// - `seat`: seat of the hand, which can be a dummy hand
+/// - `user`: user who placed the card, the president for the dummy hand
+/// - `best`: best points of the ruling party before placing the card, `None` if the search gave up
+/// - `loss`: points the party of the seat lost by placing the card instead of the best one,
+///   `None` if the search gave up before or after placing the card
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Move {
+    pub seat: usize,
+    pub user: usize,
+    pub card: Card,
+    pub best: Option<u8>,
+    pub loss: Option<u8>,
+}
+
+/// Double-dummy review of a finished game, where every hand is known to everyone
+///
+/// - `ruling`: bitmask of the seats in the ruling party at the end of the game
+/// - `points`: points of the ruling party in the game, including dropped cards by `Rule::kitty`
+/// - `moves`: every placed card in play order
+/// - `skipped`: indices of the moves whose position the search gave up, in play order
+///
+/// The ruling party tries to take the most points and the opposition the least,
+/// from every position after the friend is selected.
+/// Parties are fixed to the ones at the end, even if the friend is decided during the play.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Review {
+    pub ruling: u16,
+    pub points: u8,
+    pub moves: Vec<Move>,
+    pub skipped: Vec<usize>,
+}
+
+impl Review {
+    /// Reviews the finished game with the trick history.
+    ///
+    /// Positions are searched from the end of the game, so that earlier ones can reuse the results.
+    /// The search visits at most `limit` positions in total, and the rest of it is shared evenly
+    /// by the positions left, so that the early moves are searched too.
+    /// Positions which need more than their share are skipped, and reported in `skipped`.
+    pub fn analyze(rule: &Rule, state: &State, limit: usize) -> Result<Review> {
+        let (mut review, positions) = Review::replay(rule, state)?;
+        let dropped_points = review.points - Solver::captured(review.ruling, positions.last().unwrap());
+        let mut solver = Solver {
+            rule,
+            ruling: review.ruling,
+            table: HashMap::new(),
+            nodes: 0,
+            limit: 0,
+        };
+        let mut budget = limit;
+        let mut after = Some(review.points - dropped_points);
+        for (i, position) in positions.iter().enumerate().rev().skip(1) {
+            // `i + 1` positions are left including this one
+            solver.nodes = 0;
+            solver.limit = budget / (i + 1);
+            // history is not needed for the search, and it is cloned on every move
+            let mut position = position.clone();
+            if let State::InGame { tricks, .. } = &mut position {
+                tricks.clear();
+            }
+            let best = solver.search(&position, 0, u8::MAX);
+            budget -= solver.nodes.min(solver.limit);
+            let is_ruling = review.ruling & 1 << review.moves[i].seat != 0;
+            let m = &mut review.moves[i];
+            match best {
+                Some(best) => {
+                    m.best = Some(best + dropped_points);
+                    m.loss = after.map(|after| if is_ruling { best - after } else { after - best });
+                }
+                None => review.skipped.push(i),
+            }
+            after = best;
+        }
+        review.skipped.reverse();
+        Ok(review)
+    }
+
+    /// Review without analysis, and every position of the play from the start
+    fn replay(rule: &Rule, state: &State) -> Result<(Review, Vec<State>)> {
+        let (president, friend, pledge, giruda, dropped, points, tricks) = match state {
+            State::GameEnded {
+                president,
+                friend,
+                pledge,
+                giruda,
+                dropped,
+                points,
+                tricks,
+                ..
+            } if !tricks.is_empty() => (*president, *friend, *pledge, *giruda, dropped, *points, tricks),
+            _ => return Err(Error::NoReview),
+        };
+
+        // hands are recovered from the placed cards, and the friend is fixed from the start
+        let mut deck = vec![Vec::new(); rule.seat_cnt()];
+        for trick in tricks.iter() {
+            for (i, (card, _)) in trick.cards.iter().enumerate() {
+                deck[(trick.leader + i) % rule.seat_cnt()].push(*card);
+            }
+        }
+        for hand in deck.iter_mut() {
+            hand.sort();
+        }
+        let mut positions = vec![State::InGame {
+            president,
+            friend_func: friend.map_or(FriendFunc::None, FriendFunc::ByUser),
+            friend,
+            is_friend_known: true,
+            giruda,
+            pledge,
+            deck,
+            dropped: dropped.clone(),
+            score_deck: vec![Vec::new(); rule.seat_cnt()],
+            tricks: Vec::new(),
+            turn_count: 0,
+            placed_cards: vec![(Card::Normal(Pattern::Spade, 0), CardPolicy::Valid); rule.seat_cnt()],
+            start_user: tricks[0].leader,
+            current_user: tricks[0].leader,
+            current_pattern: Rush::from(Pattern::Spade),
+            joker_call_card: None,
+            joker_call_effect: false,
+        }];
+        let mut moves = Vec::new();
+        for trick in tricks.iter() {
+            for (i, (card, _)) in trick.cards.iter().enumerate() {
+                let position = positions.last().unwrap();
+                let user = position.valid_users(rule).trailing_zeros() as usize;
+                let cmd = if i == 0 {
+                    Command::Go(*card, trick.rush, trick.joker_call)
+                } else {
+                    Command::Go(*card, Rush::from(*card), false)
+                };
+                positions.push(position.next(user, cmd, rule)?);
+                moves.push(Move {
+                    seat: (trick.leader + i) % rule.seat_cnt(),
+                    user,
+                    card: *card,
+                    best: None,
+                    loss: None,
+                });
+            }
+        }
+
+        let ruling = friend.map_or(0, |f| 1 << f) | 1 << president | rule.dummy_seats();
+        let review = Review {
+            ruling,
+            points,
+            moves,
+            skipped: Vec::new(),
+        };
+        Ok((review, positions))
+    }
+
+    /// The biggest mistake of each user, `None` if the user lost no point
+    pub fn mistakes(&self, user_cnt: usize) -> Vec<Option<&Move>> {
+        let mut mistakes: Vec<Option<&Move>> = vec![None; user_cnt];
+        for m in self.moves.iter().filter(|m| m.loss.unwrap_or(0) > 0) {
+            if !matches!(mistakes[m.user], Some(x) if x.loss >= m.loss) {
+                mistakes[m.user] = Some(m);
+            }
+        }
+        mistakes
+    }
+}
+
+/// Remaining hands and the leader at the start of the trick
+type Position = (Vec<Vec<Card>>, usize);
+
+/// Alpha-beta search of the points taken by the ruling party, without dropped cards
+struct Solver<'a> {
+    rule: &'a Rule,
+    ruling: u16,
+    // bounds of the points taken from the position
+    table: HashMap<Position, (u8, u8)>,
+    nodes: usize,
+    limit: usize,
+}
+
+impl Solver<'_> {
+    /// Points taken by the seats in `ruling`
+    fn captured(ruling: u16, state: &State) -> u8 {
+        let score_deck = match state {
+            State::InGame { score_deck, .. } | State::GameEnded { score_deck, .. } => score_deck,
+            _ => return 0,
+        };
+        score_deck
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| ruling & 1 << i != 0)
+            .map(|(_, d)| d.len() as u8)
+            .sum()
+    }
+
+    /// Best points of the ruling party at the end, `None` if the search visited more than `limit` positions.
+    /// The result is exact if it is between `alpha` and `beta`, otherwise it is a bound.
+    fn search(&mut self, state: &State, mut alpha: u8, mut beta: u8) -> Option<u8> {
+        let (deck, start_user, current_user) = match state {
+            State::InGame {
+                deck,
+                start_user,
+                current_user,
+                ..
+            } => (deck, *start_user, *current_user),
+            _ => return Some(Solver::captured(self.ruling, state)),
+        };
+        let captured = Solver::captured(self.ruling, state);
+        let key = if start_user == current_user {
+            Some((deck.clone(), start_user))
+        } else {
+            None
+        };
+        if let Some((lower, upper)) = key.as_ref().and_then(|k| self.table.get(k)) {
+            let (lower, upper) = (lower + captured, upper.saturating_add(captured));
+            if lower >= beta || lower == upper {
+                return Some(lower);
+            }
+            if upper <= alpha {
+                return Some(upper);
+            }
+            alpha = alpha.max(lower);
+            beta = beta.min(upper);
+        }
+        let (alpha_start, beta_start) = (alpha, beta);
+        self.nodes += 1;
+        if self.nodes > self.limit {
+            return None;
+        }
+
+        let is_ruling = self.ruling & 1 << current_user != 0;
+        let user = state.valid_users(self.rule).trailing_zeros() as usize;
+        let mut best = if is_ruling { 0 } else { u8::MAX };
+        let rule = self.rule;
+        let children = state
+            .candidate_commands(user, rule, Vec::new())
+            .into_iter()
+            .filter_map(|cmd| state.next(user, cmd, rule).ok());
+        for next in children {
+            let value = self.search(&next, alpha, beta)?;
+            if is_ruling {
+                best = best.max(value);
+                alpha = alpha.max(value);
+            } else {
+                best = best.min(value);
+                beta = beta.min(value);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        if let Some(key) = key {
+            let entry = self.table.entry(key).or_insert((0, u8::MAX));
+            if best < beta_start {
+                entry.1 = entry.1.min(best - captured);
+            }
+            if best > alpha_start {
+                entry.0 = entry.0.max(best - captured);
+            }
+        }
+        Some(best)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rule::Preset;
+    use crate::state::Phase;
+
+    #[test]
+    fn review_test() {
+        let rule = Rule::from(Preset::Default5);
+        let mut state = State::new(&rule);
+        assert_eq!(Review::analyze(&rule, &state, 100), Err(Error::NoReview));
+        while state.phase() != Phase::GameEnded {
+            let user = state.valid_users(&rule).trailing_zeros() as usize;
+            state = state.next(user, Command::Random, &rule).unwrap();
+        }
+        let points = match &state {
+            State::GameEnded { points, .. } => *points,
+            _ => unreachable!(),
+        };
+
+        let review = Review::analyze(&rule, &state, 2000).unwrap();
+        assert_eq!(review.points, points);
+        assert_eq!(review.moves.len(), 50);
+        // every card of the last trick is forced
+        for m in review.moves[45..].iter() {
+            assert_eq!((m.best, m.loss), (Some(points), Some(0)));
+        }
+        let skipped = (0..50).filter(|i| review.moves[*i].best.is_none()).collect::<Vec<_>>();
+        assert_eq!(review.skipped, skipped);
+        for (m, next) in review.moves.iter().zip(review.moves[1..].iter()) {
+            if let (Some(best), Some(after), Some(loss)) = (m.best, next.best, m.loss) {
+                let is_ruling = review.ruling & 1 << m.seat != 0;
+                assert_eq!(loss, if is_ruling { best - after } else { after - best });
+            }
+        }
+        let mistakes = review.mistakes(5);
+        for (user, m) in mistakes.iter().enumerate() {
+            if let Some(m) = m {
+                assert_eq!(m.user, user);
+                assert!(review.moves.iter().filter(|x| x.user == user).all(|x| x.loss <= m.loss));
+            }
+        }
+    }
+}
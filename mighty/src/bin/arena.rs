@@ -0,0 +1,255 @@
+//! Plays bots against each other and reports how strong they are.
+//!
+//! ```text
+//! mighty-arena [--preset Default5] [--deals 100] [--seed 0] [--format json|csv] heuristic random
+//! ```
+//!
+//! Every deal is played once for each permutation of the bots, where the user `i` is played by
+//! the `i % bots`-th bot of the permutation, so that every bot plays every hand of the deal equally often.
+//! Games are played from `seed`, `seed + 1`, ... so that the results can be reproduced.
+
+use mighty::prelude::{next_user, Bot, HeuristicBot, Phase, Preset, RandomBot, Rule, State};
+use rand::{rngs::StdRng, SeedableRng};
+use serde::Serialize;
+use std::process::exit;
+
+/// z-score of the 95% confidence interval
+const Z: f64 = 1.96;
+
+const USAGE: &str = "usage: mighty-arena [--preset <preset>] [--deals <n>] [--seed <n>] [--format json|csv] <bot>...
+bots: heuristic, random";
+
+struct Options {
+    preset: Preset,
+    deals: u64,
+    seed: u64,
+    csv: bool,
+    bots: Vec<String>,
+}
+
+/// Results of a bot over every game, which are counted for each seat the bot played
+#[derive(Debug, Default)]
+struct Record {
+    games: u64,
+    wins: u64,
+    president: u64,
+    president_wins: u64,
+    president_scores: Vec<i32>,
+}
+
+/// Summary of a bot, with 95% confidence intervals
+#[derive(Debug, Serialize)]
+struct Summary {
+    bot: String,
+    games: u64,
+    win_rate: f64,
+    win_rate_ci: (f64, f64),
+    president: u64,
+    president_win_rate: f64,
+    president_score: f64,
+    president_score_ci: (f64, f64),
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    preset: Preset,
+    deals: u64,
+    seed: u64,
+    bots: Vec<Summary>,
+}
+
+fn bot(name: &str) -> Option<Box<dyn Bot>> {
+    match name {
+        "heuristic" => Some(Box::new(HeuristicBot)),
+        "random" => Some(Box::new(RandomBot)),
+        _ => None,
+    }
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        preset: Preset::Default5,
+        deals: 100,
+        seed: 0,
+        csv: false,
+        bots: Vec::new(),
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value of {}", arg));
+        match arg.as_str() {
+            "--preset" => {
+                let name = value()?;
+                options.preset =
+                    serde_json::from_value(name.clone().into()).map_err(|_| format!("unknown preset {}", name))?;
+            }
+            "--deals" => options.deals = value()?.parse().map_err(|_| "invalid count of deals".to_owned())?,
+            "--seed" => options.seed = value()?.parse().map_err(|_| "invalid seed".to_owned())?,
+            "--format" => {
+                options.csv = match value()?.as_str() {
+                    "json" => false,
+                    "csv" => true,
+                    x => return Err(format!("unknown format {}", x)),
+                }
+            }
+            "-h" | "--help" => return Err(String::new()),
+            name if bot(name).is_some() => options.bots.push(name.to_owned()),
+            x => return Err(format!("unknown argument {}", x)),
+        }
+    }
+    if options.bots.is_empty() {
+        return Err("no bot is given".to_owned());
+    }
+    Ok(options)
+}
+
+/// Every order of `0..n`
+fn permutations(n: usize) -> Vec<Vec<usize>> {
+    if n == 0 {
+        return vec![Vec::new()];
+    }
+    let mut res = Vec::new();
+    for perm in permutations(n - 1) {
+        for i in 0..n {
+            let mut perm = perm.clone();
+            perm.insert(i, n - 1);
+            res.push(perm);
+        }
+    }
+    res
+}
+
+/// Plays the game to the end, and returns the scores of the users and the president.
+/// `players[i]` is the index of the bot which plays the user `i`.
+/// Every random choice of the game is made from `seed`, including the ones of the bots.
+fn play(rule: &Rule, seed: u64, bots: &mut [Box<dyn Bot>], players: &[usize]) -> (Vec<i32>, usize) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut state = State::from_rng(rule, &mut rng);
+    while state.phase() != Phase::GameEnded {
+        let user = next_user(rule, &state);
+        let cmd = bots[players[user]].command(rule, &state, user);
+        state = state
+            .next_with_rng(user, cmd, rule, &mut rng)
+            .unwrap_or_else(|e| panic!("{} from {} in {:?}", e, bots[players[user]].name(), state));
+    }
+    match state {
+        State::GameEnded { scores, president, .. } => (scores, president),
+        _ => unreachable!(),
+    }
+}
+
+/// Wilson score interval of the ratio
+fn ratio_interval(success: u64, total: u64) -> (f64, f64) {
+    if total == 0 {
+        return (0.0, 1.0);
+    }
+    let n = total as f64;
+    let p = success as f64 / n;
+    let center = (p + Z * Z / (2.0 * n)) / (1.0 + Z * Z / n);
+    let margin = Z / (1.0 + Z * Z / n) * (p * (1.0 - p) / n + Z * Z / (4.0 * n * n)).sqrt();
+    (center - margin, center + margin)
+}
+
+/// Mean and its normal interval
+fn mean_interval(values: &[i32]) -> (f64, (f64, f64)) {
+    if values.is_empty() {
+        return (0.0, (0.0, 0.0));
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().map(|v| *v as f64).sum::<f64>() / n;
+    if values.len() == 1 {
+        return (mean, (mean, mean));
+    }
+    let variance = values.iter().map(|v| (*v as f64 - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    let margin = Z * (variance / n).sqrt();
+    (mean, (mean - margin, mean + margin))
+}
+
+fn summarize(name: &str, record: &Record) -> Summary {
+    let ratio = |x: u64, n: u64| if n == 0 { 0.0 } else { x as f64 / n as f64 };
+    let (president_score, president_score_ci) = mean_interval(&record.president_scores);
+    Summary {
+        bot: name.to_owned(),
+        games: record.games,
+        win_rate: ratio(record.wins, record.games),
+        win_rate_ci: ratio_interval(record.wins, record.games),
+        president: record.president,
+        president_win_rate: ratio(record.president_wins, record.president),
+        president_score,
+        president_score_ci,
+    }
+}
+
+fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("{}", e);
+            }
+            eprintln!("{}", USAGE);
+            exit(2);
+        }
+    };
+    let rule = Rule::from(options.preset);
+    let user_cnt = rule.user_cnt as usize;
+    let mut bots = options.bots.iter().map(|name| bot(name).unwrap()).collect::<Vec<_>>();
+    let mut records = options.bots.iter().map(|_| Record::default()).collect::<Vec<_>>();
+
+    let perms = permutations(bots.len());
+    for deal in 0..options.deals {
+        let seed = options.seed.wrapping_add(deal);
+        for perm in perms.iter() {
+            let players = (0..user_cnt).map(|user| perm[user % perm.len()]).collect::<Vec<_>>();
+            let (scores, president) = play(&rule, seed, &mut bots, &players);
+            for (user, score) in scores.iter().enumerate() {
+                let record = &mut records[players[user]];
+                record.games += 1;
+                if *score > 0 {
+                    record.wins += 1;
+                }
+                if user == president {
+                    record.president += 1;
+                    record.president_scores.push(*score);
+                    if *score > 0 {
+                        record.president_wins += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    // the same bot can be given several times, and it is reported for each time
+    let summaries = options
+        .bots
+        .iter()
+        .zip(records.iter())
+        .map(|(name, record)| summarize(name, record))
+        .collect::<Vec<_>>();
+    if options.csv {
+        println!("bot,games,win_rate,win_rate_low,win_rate_high,president,president_win_rate,president_score,president_score_low,president_score_high");
+        for s in summaries.iter() {
+            println!(
+                "{},{},{:.4},{:.4},{:.4},{},{:.4},{:.4},{:.4},{:.4}",
+                s.bot,
+                s.games,
+                s.win_rate,
+                s.win_rate_ci.0,
+                s.win_rate_ci.1,
+                s.president,
+                s.president_win_rate,
+                s.president_score,
+                s.president_score_ci.0,
+                s.president_score_ci.1
+            );
+        }
+    } else {
+        let report = Report {
+            preset: options.preset,
+            deals: options.deals,
+            seed: options.seed,
+            bots: summaries,
+        };
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    }
+}
@@ -0,0 +1,340 @@
+use crate::card::{Card, Color, Pattern};
+use crate::command::Command;
+use crate::rule::{card_policy::CardPolicy, Rule};
+use crate::state::{FriendFunc, State};
+
+/// Player which decides its command by itself, for practice and tests
+///
+/// Bots are given the whole state, but they should decide only with what the user can see.
+pub trait Bot {
+    /// Short name of the bot, used in the results of bot games
+    fn name(&self) -> &'static str;
+
+    /// Command of the user in this state, which should be accepted by `State::next`
+    fn command(&mut self, rule: &Rule, state: &State, user_id: usize) -> Command;
+}
+
+/// User whose command is waited for, when every user is played by a bot
+///
+/// Every user can pledge in the unordered election, so the first user who hasn't passed is chosen.
+pub fn next_user(rule: &Rule, state: &State) -> usize {
+    let valid_users = state.valid_users(rule);
+    match state {
+        State::Election { done, .. } => (0..rule.user_cnt as usize)
+            .find(|u| valid_users & 1 << u != 0 && !done[*u])
+            .unwrap_or(valid_users.trailing_zeros() as usize),
+        _ => valid_users.trailing_zeros() as usize,
+    }
+}
+
+/// Bot which picks uniformly from the legal commands
+#[derive(Debug, Clone, Default)]
+pub struct RandomBot;
+
+impl Bot for RandomBot {
+    fn name(&self) -> &'static str {
+        "random"
+    }
+
+    fn command(&mut self, _: &Rule, _: &State, _: usize) -> Command {
+        Command::Random
+    }
+}
+
+/// Bot which plays by rules of thumb
+///
+/// - pledges the lowest pledge which the hand can make, see `HeuristicBot::estimate`
+/// - calls the strongest card it doesn't have as the friend, and drops its weakest cards
+/// - leads the highest card of a pattern, and follows with the cheapest card which makes its party win the trick
+///
+/// Commands rejected by the rule are replaced with `Command::Random`.
+#[derive(Debug, Clone, Default)]
+pub struct HeuristicBot;
+
+impl Bot for HeuristicBot {
+    fn name(&self) -> &'static str {
+        "heuristic"
+    }
+
+    fn command(&mut self, rule: &Rule, state: &State, user_id: usize) -> Command {
+        let commands = state.legal_commands(user_id, rule);
+        let cmd = match state {
+            State::Election { deck, pledge, .. } => {
+                // pledging the same points again is accepted with no giruda, but never ends the election
+                let own = pledge[user_id].map_or(0, |(_, p)| p);
+                let commands = commands
+                    .iter()
+                    .filter(|cmd| !matches!(cmd, Command::Pledge(Some((_, p))) if *p <= own))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                HeuristicBot::pledge(rule, &deck[user_id], &commands)
+            }
+            State::SelectFriend { .. } => HeuristicBot::select_friend(rule, state, user_id),
+            State::InGame { .. } => HeuristicBot::play(rule, state, user_id, &commands),
+            State::GameEnded { .. } => None,
+        };
+        cmd.filter(|cmd| state.next(user_id, cmd.clone(), rule).is_ok())
+            .unwrap_or(Command::Random)
+    }
+}
+
+impl HeuristicBot {
+    /// Points which the hand is expected to take as the president with the giruda
+    pub fn estimate(rule: &Rule, hand: &[Card], giruda: Option<Pattern>) -> u8 {
+        let mighty = rule.mighty.get(giruda);
+        let strength = hand
+            .iter()
+            .map(|c| match c {
+                _ if Some(*c) == mighty => 3.0,
+                Card::Joker(_) => 2.5,
+                Card::Normal(p, n) if Some(*p) == giruda && *n >= 13 => 1.5,
+                Card::Normal(p, _) if Some(*p) == giruda => 1.0,
+                Card::Normal(_, 14) => 1.5,
+                Card::Normal(_, 13) => 0.5,
+                _ => 0.0,
+            })
+            .sum::<f64>();
+        // strength is measured for the hand of 10 cards, out of 20 points
+        let strength = strength * 10.0 / rule.card_cnt_per_user.max(1) as f64;
+        let points = rule.deck.0.iter().filter(|c| c.is_score()).count() as f64;
+        ((6.0 + strength) * points / 20.0) as u8
+    }
+
+    /// Value of keeping the card, higher for the cards which take tricks
+    fn strength(rule: &Rule, giruda: Option<Pattern>, card: Card) -> i32 {
+        match card {
+            _ if Some(card) == rule.mighty.get(giruda) => 60,
+            Card::Joker(_) => 50,
+            Card::Normal(p, n) if Some(p) == giruda => 20 + n as i32,
+            Card::Normal(_, n) => n as i32,
+        }
+    }
+
+    fn pledge(rule: &Rule, hand: &[Card], commands: &[Command]) -> Option<Command> {
+        commands
+            .iter()
+            .filter_map(|cmd| match cmd {
+                Command::Pledge(Some((giruda, pledge))) => {
+                    let estimate = HeuristicBot::estimate(rule, hand, *giruda);
+                    Some((*pledge, std::cmp::Reverse(estimate), cmd)).filter(|_| *pledge <= estimate)
+                }
+                _ => None,
+            })
+            .min_by_key(|(pledge, estimate, _)| (*pledge, *estimate))
+            .map(|(_, _, cmd)| cmd)
+            .or_else(|| commands.iter().find(|cmd| **cmd == Command::Pledge(None)))
+            .or_else(|| commands.first())
+            .cloned()
+    }
+
+    fn select_friend(rule: &Rule, state: &State, user_id: usize) -> Option<Command> {
+        let (president, giruda, deck, kitty) = match state {
+            State::SelectFriend {
+                president,
+                giruda,
+                deck,
+                kitty,
+                ..
+            } => (*president, *giruda, deck, kitty),
+            _ => return None,
+        };
+        if user_id != president {
+            return None;
+        }
+        let hand = &deck[user_id];
+        let mut cards = hand.clone();
+        cards.sort_by_key(|c| (c.is_score(), HeuristicBot::strength(rule, giruda, *c)));
+        cards.truncate(rule.kitty_size());
+
+        let tops = match giruda {
+            Some(p) => vec![Card::Normal(p, 14), Card::Normal(p, 13), Card::Normal(p, 12)],
+            None => [Pattern::Spade, Pattern::Diamond, Pattern::Heart, Pattern::Clover]
+                .iter()
+                .map(|p| Card::Normal(*p, 14))
+                .collect(),
+        };
+        let friend_cards = rule
+            .mighty
+            .get(giruda)
+            .into_iter()
+            .chain(vec![Card::Joker(Color::Black), Card::Joker(Color::Red)])
+            .chain(tops)
+            .filter(|c| !hand.contains(c) && rule.deck.0.contains(c))
+            .map(FriendFunc::ByCard);
+        let other = (0..rule.user_cnt as usize)
+            .find(|u| *u != president)
+            .unwrap_or(president);
+        let friend_funcs = friend_cards
+            .chain(vec![
+                FriendFunc::First,
+                FriendFunc::Last,
+                FriendFunc::ByUser(other),
+                FriendFunc::None,
+            ])
+            .collect::<Vec<_>>();
+        // the kitty can be always dropped back, even if the exchange is limited
+        vec![cards, kitty.clone()].into_iter().find_map(|drop| {
+            friend_funcs
+                .iter()
+                .map(|f| Command::SelectFriend(drop.clone(), f.clone()))
+                .find(|cmd| state.next(user_id, cmd.clone(), rule).is_ok())
+        })
+    }
+
+    fn play(rule: &Rule, state: &State, user_id: usize, commands: &[Command]) -> Option<Command> {
+        let (president, friend, is_friend_known, giruda, deck, tricks, start_user, current_user) = match state {
+            State::InGame {
+                president,
+                friend,
+                is_friend_known,
+                giruda,
+                deck,
+                tricks,
+                start_user,
+                current_user,
+                ..
+            } => (
+                *president,
+                *friend,
+                *is_friend_known,
+                *giruda,
+                deck,
+                tricks,
+                *start_user,
+                *current_user,
+            ),
+            _ => return None,
+        };
+        let card_of = |cmd: &Command| match cmd {
+            Command::Go(card, ..) => Some(*card),
+            _ => None,
+        };
+        let strength = |card: Card| HeuristicBot::strength(rule, giruda, card);
+        let is_ruling =
+            |user: usize| user == president || (friend == Some(user) && (is_friend_known || user == user_id));
+        let is_partner = |user: usize| is_ruling(user) == is_ruling(user_id);
+
+        if start_user == current_user {
+            // the highest card of a pattern takes the trick unless the others are out of the pattern
+            let hand = &deck[current_user];
+            let mut unseen = rule.deck.0.clone();
+            for card in tricks
+                .iter()
+                .flat_map(|t| t.cards.iter().map(|(c, _)| c))
+                .chain(hand.iter())
+            {
+                if let Some(i) = unseen.iter().position(|c| c == card) {
+                    unseen.swap_remove(i);
+                }
+            }
+            let is_top = |card: Card| match card {
+                Card::Normal(p, n) => !unseen
+                    .iter()
+                    .any(|c| matches!(c, Card::Normal(q, m) if *q == p && *m > n)),
+                Card::Joker(_) => false,
+            };
+            let giruda_cnt = hand
+                .iter()
+                .filter(|c| matches!(c, Card::Normal(p, _) if Some(*p) == giruda))
+                .count();
+            return commands
+                .iter()
+                .filter(|cmd| matches!(cmd, Command::Go(_, _, false)))
+                .max_by_key(|cmd| {
+                    let card = card_of(cmd).unwrap();
+                    let is_giruda = matches!(card, Card::Normal(p, _) if Some(p) == giruda);
+                    // the ruling party pulls out giruda of the opposition
+                    if Some(card) != rule.mighty.get(giruda)
+                        && is_top(card)
+                        && (!is_giruda || is_ruling(user_id) && giruda_cnt >= 3)
+                    {
+                        100 + strength(card)
+                    } else {
+                        -strength(card) - if card.is_score() { 20 } else { 0 }
+                    }
+                })
+                .cloned();
+        }
+
+        commands
+            .iter()
+            .filter_map(|cmd| {
+                let card = card_of(cmd)?;
+                let next = state.next(user_id, cmd.clone(), rule).ok()?;
+                let (winner, points) = HeuristicBot::trick_result(rule, &next, start_user, tricks.len())?;
+                let value = if is_partner(winner) { points } else { -points };
+                Some((value * 10 - strength(card), cmd))
+            })
+            .max_by_key(|(value, _)| *value)
+            .map(|(_, cmd)| cmd.clone())
+    }
+
+    /// Winner of the trick after placing the card and the points on it, as if no one places after
+    fn trick_result(rule: &Rule, next: &State, start_user: usize, trick_cnt: usize) -> Option<(usize, i32)> {
+        let points = |cards: &mut dyn Iterator<Item = Card>| cards.filter(Card::is_score).count() as i32;
+        match next {
+            State::InGame { tricks, .. } | State::GameEnded { tricks, .. } if tricks.len() > trick_cnt => {
+                let trick = tricks.last()?;
+                Some((trick.winner, points(&mut trick.cards.iter().map(|(c, _)| *c))))
+            }
+            State::InGame {
+                placed_cards,
+                current_user,
+                ..
+            } => {
+                let placed = (0..rule.seat_cnt())
+                    .map(|i| (i + start_user) % rule.seat_cnt())
+                    .take_while(|u| u != current_user)
+                    .collect::<Vec<_>>();
+                let mut cards = placed
+                    .iter()
+                    .filter(|u| placed_cards[**u].1 != CardPolicy::NoEffect)
+                    .map(|u| placed_cards[*u].0)
+                    .collect::<Vec<_>>();
+                if cards.is_empty() {
+                    cards = placed.iter().map(|u| placed_cards[*u].0).collect();
+                }
+                let winner_card = next.calculate_winner(rule, &cards);
+                let winner = *placed.iter().find(|u| placed_cards[**u].0 == winner_card)?;
+                Some((winner, points(&mut placed.iter().map(|u| placed_cards[*u].0))))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rule::Preset;
+    use crate::state::Phase;
+
+    #[test]
+    fn heuristic_bot_test() {
+        for preset in [Preset::Default5, Preset::Practice3, Preset::Double10].iter() {
+            let rule = Rule::from(*preset);
+            for _ in 0..5 {
+                let mut state = State::new(&rule);
+                while state.phase() != Phase::GameEnded {
+                    let user = next_user(&rule, &state);
+                    let cmd = HeuristicBot.command(&rule, &state, user);
+                    assert_ne!(cmd, Command::Random, "{:?}", state);
+                    state = state.next(user, cmd, &rule).unwrap();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn estimate_test() {
+        let rule = Rule::new();
+        let strong = Card::parse_hand("SA DA JK-B S10 SK SQ SJ S2 HA CA").unwrap();
+        let weak = Card::parse_hand("S2 S3 D4 D5 H6 H7 C8 C9 C2 D3").unwrap();
+        assert!(HeuristicBot::estimate(&rule, &strong, Some(Pattern::Spade)) >= rule.pledge.min);
+        assert!(HeuristicBot::estimate(&rule, &weak, Some(Pattern::Spade)) < rule.pledge.min);
+        assert_eq!(
+            HeuristicBot::pledge(&rule, &weak, &[Command::Pledge(None)]),
+            Some(Command::Pledge(None))
+        );
+    }
+}
@@ -1,6 +1,8 @@
 use bitflags::bitflags;
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
 #[cfg(feature = "client")]
 use wasm_bindgen::prelude::*;
 
@@ -134,7 +136,161 @@ pub enum Card {
     Joker(Color),
 }
 
+/// Error of parsing the notation of `Pattern`, `Card` or `Rush`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ParseCardError(pub String);
+
+impl fmt::Display for ParseCardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid card notation: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseCardError {}
+
+impl Pattern {
+    /// Unicode suit symbol: `♠`, `♦`, `♥`, `♣`
+    pub fn symbol(&self) -> char {
+        match self {
+            Pattern::Spade => '♠',
+            Pattern::Diamond => '♦',
+            Pattern::Heart => '♥',
+            Pattern::Clover => '♣',
+        }
+    }
+
+    fn from_char(c: char) -> Option<Pattern> {
+        match c {
+            'S' | 's' | '♠' | '♤' => Some(Pattern::Spade),
+            'D' | 'd' | '♦' | '♢' => Some(Pattern::Diamond),
+            'H' | 'h' | '♥' | '♡' => Some(Pattern::Heart),
+            'C' | 'c' | '♣' | '♧' => Some(Pattern::Clover),
+            _ => None,
+        }
+    }
+}
+
+/// One letter notation: `S`, `D`, `H`, `C`, or the suit symbol with `{:#}`
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "{}", self.symbol())
+        } else {
+            let c = match self {
+                Pattern::Spade => 'S',
+                Pattern::Diamond => 'D',
+                Pattern::Heart => 'H',
+                Pattern::Clover => 'C',
+            };
+            write!(f, "{}", c)
+        }
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = ParseCardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.trim().chars();
+        match (chars.next().and_then(Pattern::from_char), chars.next()) {
+            (Some(p), None) => Ok(p),
+            _ => Err(ParseCardError(s.to_owned())),
+        }
+    }
+}
+
+/// Pattern letters in the order of `S`, `D`, `H`, `C` (`SC` for black), or suit symbols with `{:#}`
+impl fmt::Display for Rush {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for p in [Pattern::Spade, Pattern::Diamond, Pattern::Heart, Pattern::Clover].iter() {
+            if self.contains(Rush::from(*p)) {
+                if f.alternate() {
+                    write!(f, "{:#}", p)?;
+                } else {
+                    write!(f, "{}", p)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Rush {
+    type Err = ParseCardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.trim().chars().try_fold(Rush::empty(), |rush, c| {
+            Pattern::from_char(c)
+                .map(|p| rush | Rush::from(p))
+                .ok_or_else(|| ParseCardError(s.to_owned()))
+        })
+    }
+}
+
+/// Short notation of the card: pattern + number (`SA`, `D10`, `HQ`), or `JK-B`, `JK-R` for jokers.
+///
+/// With `{:#}` the pattern is written as the suit symbol (`♠A`, `♦10`).
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Card::Normal(p, n) => {
+                if f.alternate() {
+                    write!(f, "{:#}", p)?;
+                } else {
+                    write!(f, "{}", p)?;
+                }
+                match n {
+                    11 => write!(f, "J"),
+                    12 => write!(f, "Q"),
+                    13 => write!(f, "K"),
+                    14 => write!(f, "A"),
+                    _ => write!(f, "{}", n),
+                }
+            }
+            Card::Joker(Color::Black) => write!(f, "JK-B"),
+            Card::Joker(Color::Red) => write!(f, "JK-R"),
+        }
+    }
+}
+
+impl FromStr for Card {
+    type Err = ParseCardError;
+
+    /// Parses the notation of `Display`, in either case and with either letters or suit symbols.
+    /// `joker-black` and `joker-red` are also accepted.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseCardError(s.to_owned());
+        let t = s.trim();
+        match t.to_uppercase().as_str() {
+            "JK-B" | "JOKER-BLACK" => return Ok(Card::Joker(Color::Black)),
+            "JK-R" | "JOKER-RED" => return Ok(Card::Joker(Color::Red)),
+            _ => {}
+        }
+        let mut chars = t.chars();
+        let pattern = chars.next().and_then(Pattern::from_char).ok_or_else(err)?;
+        let num = match chars.as_str().to_uppercase().as_str() {
+            "J" => 11,
+            "Q" => 12,
+            "K" => 13,
+            "A" => 14,
+            n if n.starts_with(|c: char| c.is_ascii_digit()) => {
+                n.parse::<u8>().ok().filter(|n| (2..=14).contains(n)).ok_or_else(err)?
+            }
+            _ => return Err(err()),
+        };
+        Ok(Card::Normal(pattern, num))
+    }
+}
+
 impl Card {
+    /// Parses a hand of cards separated by whitespace or commas, such as `SA D10 ♥Q JK-B`.
+    pub fn parse_hand(s: &str) -> Result<Vec<Card>, ParseCardError> {
+        s.split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|c| !c.is_empty())
+            .map(Card::from_str)
+            .collect()
+    }
+
     pub fn is_score(&self) -> bool {
         match self {
             Card::Normal(_, n) => *n >= 10,
@@ -147,6 +303,33 @@ impl Card {
     }
 }
 
+/// Serializes the map of cards with the notation of cards as keys,
+/// since json only allows string keys.
+///
+/// Use with `#[serde(with = "crate::card::card_map")]`.
+pub(crate) mod card_map {
+    use super::Card;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::{BTreeMap, HashMap};
+
+    pub fn serialize<S: Serializer, T: Serialize>(map: &HashMap<Card, T>, serializer: S) -> Result<S::Ok, S::Error> {
+        map.iter()
+            .map(|(c, v)| (c.to_string(), v))
+            .collect::<BTreeMap<_, _>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<Card, T>, D::Error> {
+        HashMap::<String, T>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(c, v)| c.parse::<Card>().map(|c| (c, v)).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -221,4 +404,60 @@ mod test {
         assert_eq!(Card::Joker(Color::Black).is_joker(), true);
         assert_eq!(Card::Normal(Pattern::Spade, 5).is_joker(), false);
     }
+
+    #[test]
+    fn card_notation_test() {
+        let cards = [
+            ("SA", "♠A", Card::Normal(Pattern::Spade, 14)),
+            ("H10", "♥10", Card::Normal(Pattern::Heart, 10)),
+            ("DQ", "♦Q", Card::Normal(Pattern::Diamond, 12)),
+            ("C2", "♣2", Card::Normal(Pattern::Clover, 2)),
+            ("JK-B", "JK-B", Card::Joker(Color::Black)),
+            ("JK-R", "JK-R", Card::Joker(Color::Red)),
+        ];
+        for (s, u, c) in cards.iter() {
+            assert_eq!(c.to_string(), *s);
+            assert_eq!(format!("{:#}", c), *u);
+            assert_eq!(s.parse::<Card>(), Ok(*c));
+            assert_eq!(u.parse::<Card>(), Ok(*c));
+        }
+        assert_eq!("s13".parse::<Card>(), Ok(Card::Normal(Pattern::Spade, 13)));
+        assert_eq!("♤k".parse::<Card>(), Ok(Card::Normal(Pattern::Spade, 13)));
+        assert_eq!("joker-red".parse::<Card>(), Ok(Card::Joker(Color::Red)));
+        for s in ["", "S", "S1", "S15", "S+3", "X3", "joker", "JK"].iter() {
+            assert_eq!(s.parse::<Card>(), Err(ParseCardError(s.to_string())), "{}", s);
+        }
+    }
+
+    #[test]
+    fn pattern_rush_notation_test() {
+        assert_eq!(Pattern::Diamond.to_string(), "D");
+        assert_eq!(format!("{:#}", Pattern::Diamond), "♦");
+        assert_eq!("h".parse::<Pattern>(), Ok(Pattern::Heart));
+        assert_eq!("♣".parse::<Pattern>(), Ok(Pattern::Clover));
+        assert!("SD".parse::<Pattern>().is_err());
+
+        assert_eq!(Rush::black().to_string(), "SC");
+        assert_eq!(format!("{:#}", Rush::red()), "♦♥");
+        assert_eq!(Rush::any().to_string(), "SDHC");
+        assert_eq!("cs".parse::<Rush>(), Ok(Rush::black()));
+        assert_eq!("♦H".parse::<Rush>(), Ok(Rush::red()));
+        assert_eq!("".parse::<Rush>(), Ok(Rush::empty()));
+        assert!("SX".parse::<Rush>().is_err());
+    }
+
+    #[test]
+    fn parse_hand_test() {
+        assert_eq!(
+            Card::parse_hand("SA, d10 ♥Q\tJK-B"),
+            Ok(vec![
+                Card::Normal(Pattern::Spade, 14),
+                Card::Normal(Pattern::Diamond, 10),
+                Card::Normal(Pattern::Heart, 12),
+                Card::Joker(Color::Black),
+            ])
+        );
+        assert_eq!(Card::parse_hand(" "), Ok(vec![]));
+        assert_eq!(Card::parse_hand("SA S1"), Err(ParseCardError("S1".to_owned())));
+    }
 }
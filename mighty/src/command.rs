@@ -10,5 +10,7 @@ pub enum Command {
     SelectFriend(Vec<Card>, FriendFunc),
     ChangePledge(Option<Pattern>),
     Go(Card, Rush, bool),
+    // declare missed deal and reveal the hand (before anyone pledges)
+    DeclareMissedDeal,
     Random,
 }
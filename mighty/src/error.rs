@@ -1,52 +1,287 @@
+use crate::card::{Card, ParseCardError, Pattern, Rush};
+use crate::state::{FriendFunc, Phase};
+
+/// Rejected command or message, with the context of the rejection.
+///
+/// `code` gives the stable identifier for each variant and `Display` gives the english message.
 #[derive(PartialEq, Clone, Debug)]
 pub enum Error {
-    ParseError,
-    InvalidCommand(&'static str),
-    InvalidPledge(bool, u8),
-    InvalidOrder,
-    InvalidUser,
-    InvalidFriendFunc,
-    NotLeader,
-    NotPresident,
-    DropCard,
-    NotInDeck,
-    SameGiruda,
-    WrongPattern,
-    Internal(&'static str),
-    WrongCard,
+    /// Malformed message, with the reason from the parser
+    ParseError(String),
+    /// The command is not allowed in this phase
+    InvalidCommand(Phase),
+    /// The user is not a player of the game
+    NotPlayer,
+    /// The user tried to play out of turn
+    NotYourTurn(usize),
+    /// Pledge is higher than the maximum of the rule
+    PledgeTooHigh(u8),
+    /// Pledge is lower than the minimum, or the current highest pledge (with offsets applied)
+    PledgeTooLow(u8),
+    /// Pledge should be strictly higher than the current pledge under `Election::INCREASING`
+    PledgeNotIncreasing(u8),
+    /// Pledge without giruda while the rule doesn't allow it
+    NoGiruda,
+    /// The user already passed the election
+    AlreadyPassed(usize),
+    /// The first user passed while the rule doesn't allow it
     PassFirst,
-    JokerCall,
+    /// Missed deal can be declared only under `Declare::Voluntary`
+    DeclareNotAllowed,
+    /// Missed deal can be declared only before anyone pledges or passes
+    ElectionStarted,
+    /// The hand doesn't satisfy the missed deal rule
+    NotMissedDeal,
+    /// Wrong count of dropped cards (expected, actual)
+    DropCard(usize, usize),
+    /// The card is not in the hand of the user
+    NotInDeck(Card),
+    /// The president kept more cards of the kitty than `Kitty::max_exchange`
+    KittyExchange(u8),
+    /// The friend function is not allowed by the rule
+    FriendNotAllowed(FriendFunc),
+    /// The president has the friend card while fake friend is not allowed
+    FakeFriend(Card),
+    /// The user can't be the friend
+    InvalidFriend(usize),
+    /// Changed giruda is same as the current one
+    SameGiruda(Option<Pattern>),
+    /// The card can't be placed on the trick by the card policy (card, turn count)
+    RestrictedCard(Card, u8),
+    /// Giruda can't lead while the user holds other cards
+    GirudaLead(Pattern),
+    /// The user should follow the leading pattern
+    MustFollow(Rush),
+    /// The joker can't lead with the pattern of other color (joker, pattern)
+    WrongPattern(Card, Rush),
+    /// The joker is called by the card, so the user should place the joker
+    JokerCall(Card),
+    /// The user has no command to take back, or the next user already played
+    NoUndo,
+    /// Only finished games with the trick history can be reviewed
+    NoReview,
+    /// Bug or unexpected state of the server
+    Internal(&'static str),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
 impl std::error::Error for Error {}
 
+impl Error {
+    /// Short identifier of the error which doesn't change through versions.
+    /// Clients can use this to show their own messages.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::ParseError(_) => "parse_error",
+            Error::InvalidCommand(_) => "invalid_command",
+            Error::NotPlayer => "not_player",
+            Error::NotYourTurn(_) => "not_your_turn",
+            Error::PledgeTooHigh(_) => "pledge_too_high",
+            Error::PledgeTooLow(_) => "pledge_too_low",
+            Error::PledgeNotIncreasing(_) => "pledge_not_increasing",
+            Error::NoGiruda => "no_giruda",
+            Error::AlreadyPassed(_) => "already_passed",
+            Error::PassFirst => "pass_first",
+            Error::DeclareNotAllowed => "declare_not_allowed",
+            Error::ElectionStarted => "election_started",
+            Error::NotMissedDeal => "not_missed_deal",
+            Error::DropCard(..) => "drop_card",
+            Error::NotInDeck(_) => "not_in_deck",
+            Error::KittyExchange(_) => "kitty_exchange",
+            Error::FriendNotAllowed(_) => "friend_not_allowed",
+            Error::FakeFriend(_) => "fake_friend",
+            Error::InvalidFriend(_) => "invalid_friend",
+            Error::SameGiruda(_) => "same_giruda",
+            Error::RestrictedCard(..) => "restricted_card",
+            Error::GirudaLead(_) => "giruda_lead",
+            Error::MustFollow(_) => "must_follow",
+            Error::WrongPattern(..) => "wrong_pattern",
+            Error::JokerCall(_) => "joker_call",
+            Error::NoUndo => "no_undo",
+            Error::NoReview => "no_review",
+            Error::Internal(_) => "internal",
+        }
+    }
+}
+
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Error::ParseError => write!(f, "parse error"),
-            Error::InvalidCommand(c) => write!(f, "invalid command, expected: {}", c),
-            Error::InvalidPledge(..) => write!(f, "invalid pledge"),
-            Error::InvalidOrder => write!(f, "invalid order"),
-            Error::InvalidUser => write!(f, "invalid user"),
-            Error::InvalidFriendFunc => write!(f, "invalid friend function"),
-            Error::NotLeader => write!(f, "you are not the leader"),
-            Error::NotPresident => write!(f, "you are not the president"),
-            Error::DropCard => write!(f, "you should drop more or less cards"),
-            Error::NotInDeck => write!(f, "the card is not in the deck"),
-            Error::WrongPattern => write!(f, "your card has wrong pattern"),
-            Error::SameGiruda => write!(f, "same giruda"),
+            Error::ParseError(e) => write!(f, "parse error: {}", e),
+            Error::InvalidCommand(p) => write!(f, "the command is not allowed during {}", p),
+            Error::NotPlayer => write!(f, "you are not a player of this game"),
+            Error::NotYourTurn(u) => write!(f, "it is not the turn of user {}", u),
+            Error::PledgeTooHigh(m) => write!(f, "pledge can't exceed {}", m),
+            Error::PledgeTooLow(m) => write!(f, "pledge should be at least {}", m),
+            Error::PledgeNotIncreasing(m) => write!(f, "pledge should be higher than {}", m),
+            Error::NoGiruda => write!(f, "pledge without giruda is not allowed under this rule"),
+            Error::AlreadyPassed(u) => write!(f, "user {} already passed the election", u),
+            Error::PassFirst => write!(f, "the first user can't pass under this rule"),
+            Error::DeclareNotAllowed => write!(f, "declaring missed deal is not allowed under this rule"),
+            Error::ElectionStarted => write!(f, "missed deal should be declared before anyone pledges or passes"),
+            Error::NotMissedDeal => write!(f, "your hand is not a missed deal"),
+            Error::DropCard(e, a) => write!(f, "you should drop {} cards, not {}", e, a),
+            Error::NotInDeck(c) => write!(f, "the card {} is not in the deck", c),
+            Error::KittyExchange(m) => write!(f, "you can keep at most {} cards of the kitty", m),
+            Error::FriendNotAllowed(func) => {
+                let name = match func {
+                    FriendFunc::None => "playing without friend",
+                    FriendFunc::ByCard(_) => "friend by card",
+                    FriendFunc::ByUser(_) => "picking the friend",
+                    FriendFunc::First => "friend of the first trick",
+                    FriendFunc::Last => "friend of the last trick",
+                };
+                write!(f, "{} is not allowed under this rule", name)
+            }
+            Error::FakeFriend(c) => write!(f, "you have {}, and fake friend is not allowed under this rule", c),
+            Error::InvalidFriend(u) => write!(f, "user {} can't be the friend", u),
+            Error::SameGiruda(Some(p)) => write!(f, "the giruda is already {:#}", p),
+            Error::SameGiruda(None) => write!(f, "the game is already without giruda"),
+            Error::RestrictedCard(c, 0) => write!(f, "{} can't be placed on the first trick under this rule", c),
+            Error::RestrictedCard(c, _) => write!(f, "{} can't be placed on the last trick under this rule", c),
+            Error::GirudaLead(p) => write!(f, "giruda {:#} can't lead while holding other cards under this rule", p),
+            Error::MustFollow(r) => write!(f, "you must follow {0:#} while holding {0:#}", r),
+            Error::WrongPattern(c, r) => write!(f, "{} can't lead {:#}", c, r),
+            Error::JokerCall(c) => write!(f, "the joker is called by {}, you need to place the joker", c),
+            Error::NoUndo => write!(f, "you have no command to take back"),
+            Error::NoReview => write!(f, "only finished games with the trick history can be reviewed"),
             Error::Internal(e) => write!(f, "internal error: {}", e),
-            Error::WrongCard => write!(f, "you can't place this card"),
-            Error::PassFirst => write!(f, "dealer should run at first turn"),
-            Error::JokerCall => write!(f, "you need to place the joker"),
+        }
+    }
+}
+
+/// Violated constraint of the rule, returned by `Rule::validate`
+#[derive(PartialEq, Clone, Debug)]
+pub enum RuleError {
+    /// `user_cnt` is zero, or too many seats with the dummy hands
+    UserCount(u8),
+    /// `card_cnt_per_user` is zero
+    CardCount,
+    /// Needed cards, cards in the deck
+    DeckSize(usize, usize),
+    /// Colors of the joker in the deck, joker calls
+    JokerCall(usize, usize),
+    /// Mighty card is not in the deck
+    Mighty,
+    /// `pattern_order` is not a permutation of four patterns
+    PatternOrder,
+    /// Minimum & maximum of the pledge
+    PledgeRange(u8, u8),
+    /// Scoring base is not lower than the minimum pledge, or a multiplier is zero
+    Scoring,
+    /// No friend function is allowed or unknown flag is set
+    Friend,
+    /// Unknown flag is set
+    Visibility,
+    /// One turn time is longer than total time of the state
+    Timing(&'static str),
+}
+
+impl std::error::Error for RuleError {}
+
+impl RuleError {
+    /// Short identifier of the error which doesn't change through versions.
+    pub fn code(&self) -> &'static str {
+        match self {
+            RuleError::UserCount(_) => "user_count",
+            RuleError::CardCount => "card_count",
+            RuleError::DeckSize(..) => "deck_size",
+            RuleError::JokerCall(..) => "joker_call_count",
+            RuleError::Mighty => "mighty_not_in_deck",
+            RuleError::PatternOrder => "pattern_order",
+            RuleError::PledgeRange(..) => "pledge_range",
+            RuleError::Scoring => "scoring",
+            RuleError::Friend => "friend",
+            RuleError::Visibility => "visibility",
+            RuleError::Timing(_) => "timing",
+        }
+    }
+}
+
+impl std::fmt::Display for RuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleError::UserCount(c) => write!(
+                f,
+                "user count should be between 1 and 16 with the dummy hands, not {}",
+                c
+            ),
+            RuleError::CardCount => write!(f, "each user should get at least one card"),
+            RuleError::DeckSize(need, has) => write!(f, "{} cards are needed, but the deck has {}", need, has),
+            RuleError::JokerCall(jokers, calls) => {
+                write!(
+                    f,
+                    "{} kinds of jokers are in the deck, but {} joker calls are set",
+                    jokers, calls
+                )
+            }
+            RuleError::Mighty => write!(f, "the mighty card should be in the deck"),
+            RuleError::PatternOrder => write!(f, "pattern order should contain every pattern once"),
+            RuleError::PledgeRange(min, max) => write!(f, "minimum pledge {} should be less than maximum {}", min, max),
+            RuleError::Scoring => write!(
+                f,
+                "scoring base should be less than the minimum pledge, and multipliers should not be zero"
+            ),
+            RuleError::Friend => write!(f, "at least one valid way to select friend is needed"),
+            RuleError::Visibility => write!(f, "invalid visibility"),
+            RuleError::Timing(s) => write!(f, "one turn time of {} should not exceed the total time", s),
         }
     }
 }
 
 impl From<serde_json::Error> for Error {
-    fn from(_: serde_json::Error) -> Self {
-        Error::ParseError
+    fn from(e: serde_json::Error) -> Self {
+        Error::ParseError(e.to_string())
+    }
+}
+
+impl From<ParseCardError> for Error {
+    fn from(e: ParseCardError) -> Self {
+        Error::ParseError(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::card::Color;
+
+    #[test]
+    fn error_message_test() {
+        let errors = [
+            (
+                Error::MustFollow(Rush::HEART),
+                "must_follow",
+                "you must follow ♥ while holding ♥",
+            ),
+            (
+                Error::NotInDeck(Card::Normal(Pattern::Diamond, 10)),
+                "not_in_deck",
+                "the card D10 is not in the deck",
+            ),
+            (
+                Error::RestrictedCard(Card::Joker(Color::Black), 0),
+                "restricted_card",
+                "JK-B can't be placed on the first trick under this rule",
+            ),
+            (
+                Error::FriendNotAllowed(FriendFunc::ByUser(2)),
+                "friend_not_allowed",
+                "picking the friend is not allowed under this rule",
+            ),
+            (
+                Error::InvalidCommand(Phase::Election),
+                "invalid_command",
+                "the command is not allowed during the election",
+            ),
+        ];
+        for (e, code, message) in errors.iter() {
+            assert_eq!(e.code(), *code);
+            assert_eq!(e.to_string(), *message);
+        }
+        let e = Error::from(serde_json::from_str::<Card>("1").unwrap_err());
+        assert_eq!(e.code(), "parse_error");
+        assert_ne!(e.to_string(), "parse error: ");
     }
 }
@@ -8,7 +8,11 @@ use serde::{Deserialize, Serialize};
 pub struct Game {
     pub rule: Rule,
     pub state: State,
-    valid_users: u8,
+    valid_users: u16,
+    // last command which can be taken back, (user, state before the command)
+    // only one command is kept, so it is gone when anyone plays next
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    undo: Option<(usize, State)>,
 }
 
 impl Game {
@@ -19,10 +23,11 @@ impl Game {
             rule,
             state,
             valid_users,
+            undo: None,
         }
     }
 
-    pub fn valid_users(&self) -> u8 {
+    pub fn valid_users(&self) -> u16 {
         self.valid_users
     }
 
@@ -31,16 +36,61 @@ impl Game {
     }
 
     pub fn next(&mut self, user_id: usize, cmd: Command) -> Result<bool> {
-        if self.valid_users & (1u8 << user_id) > 0 {
-            self.state = self.state.next(user_id, cmd, &self.rule)?;
+        if self.valid_users & (1u16 << user_id) > 0 {
+            let state = self.state.next(user_id, cmd, &self.rule)?;
+            let prev = std::mem::replace(&mut self.state, state);
             self.valid_users = self.state.valid_users(&self.rule);
+            self.undo = Some((user_id, prev)).filter(|_| !self.is_finished());
             Ok(self.valid_users == 0)
         } else {
-            Err(Error::InvalidUser)
+            Err(Error::NotYourTurn(user_id))
         }
     }
 
+    /// Whether the user played the last command, and it can be taken back
+    pub fn can_undo(&self, user_id: usize) -> bool {
+        matches!(self.undo, Some((u, _)) if u == user_id)
+    }
+
+    /// Takes back the last command of the user, before the next user plays.
+    pub fn undo(&mut self, user_id: usize) -> Result<()> {
+        if !self.can_undo(user_id) {
+            return Err(Error::NoUndo);
+        }
+        let (_, state) = self.undo.take().unwrap();
+        self.state = state;
+        self.valid_users = self.state.valid_users(&self.rule);
+        Ok(())
+    }
+
     pub fn get_state(&self) -> State {
         self.state.clone()
     }
 }
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "server")]
+    use {super::*, crate::rule::Preset};
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn undo_test() {
+        let mut game = Game::new(Rule::from(Preset::Default5));
+        assert_eq!(game.undo(0), Err(Error::NoUndo));
+
+        let user = game.valid_users().trailing_zeros() as usize;
+        let (state, valid_users) = (game.get_state(), game.valid_users());
+        game.next(user, Command::Pledge(None)).unwrap();
+        assert!(!game.can_undo((user + 1) % 5));
+        game.undo(user).unwrap();
+        assert_eq!((game.get_state(), game.valid_users()), (state, valid_users));
+        assert_eq!(game.undo(user), Err(Error::NoUndo));
+
+        game.next(user, Command::Pledge(None)).unwrap();
+        let next = game.valid_users().trailing_zeros() as usize;
+        game.next(next, Command::Pledge(None)).unwrap();
+        assert_eq!(game.undo(user), Err(Error::NoUndo));
+        assert!(game.can_undo(next));
+    }
+}
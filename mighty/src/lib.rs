@@ -1,3 +1,8 @@
+mod advisor;
+#[cfg(feature = "server")]
+mod analysis;
+#[cfg(feature = "server")]
+mod bot;
 mod card;
 mod command;
 pub mod error;
@@ -7,10 +12,15 @@ mod rule;
 mod state;
 
 pub mod prelude {
-    pub use crate::card::{Card, Color, Pattern, Rush};
+    pub use crate::advisor::Advice;
+    #[cfg(feature = "server")]
+    pub use crate::analysis::{Move, Review};
+    #[cfg(feature = "server")]
+    pub use crate::bot::{next_user, Bot, HeuristicBot, RandomBot};
+    pub use crate::card::{Card, Color, ParseCardError, Pattern, Rush};
     pub use crate::command::Command;
     #[cfg(feature = "server")]
     pub use crate::game::Game;
     pub use crate::rule::prelude::*;
-    pub use crate::state::{FriendFunc, State};
+    pub use crate::state::{FriendFunc, Phase, State, Trick};
 }
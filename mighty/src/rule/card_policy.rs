@@ -21,6 +21,7 @@ pub struct Policy {
     pub giruda: (CardPolicy, CardPolicy),
     pub joker: (CardPolicy, CardPolicy),
     pub joker_call: (CardPolicy, CardPolicy),
+    #[serde(with = "crate::card::card_map")]
     pub card: HashMap<Card, (CardPolicy, CardPolicy)>,
 }
 
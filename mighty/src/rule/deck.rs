@@ -9,6 +9,8 @@ pub enum Preset {
     FullDeck,
     #[serde(rename = "o")]
     SingleJoker,
+    #[serde(rename = "d")]
+    DoubleDeck,
 }
 
 impl Preset {
@@ -64,6 +66,7 @@ impl From<Preset> for DeckBuilder {
 
                 DeckBuilder(s)
             }
+            Preset::DoubleDeck => DeckBuilder::from(Preset::FullDeck).double(),
         }
     }
 }
@@ -154,3 +157,20 @@ impl DeckBuilder {
 ///    └-- Is Black Joker valid
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, Hash)]
 pub struct Deck(pub Vec<Card>, pub u8);
+
+/// Winner among the identical cards, for the deck with duplicate cards
+///
+/// - `First`: the card placed first wins
+/// - `Last`: the card placed last wins
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize, Hash, Default)]
+pub enum TieBreak {
+    #[default]
+    First,
+    Last,
+}
+
+impl TieBreak {
+    pub fn is_default(&self) -> bool {
+        *self == TieBreak::First
+    }
+}
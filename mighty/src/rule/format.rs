@@ -0,0 +1,464 @@
+//! # Rule file format
+//!
+//! Rules can be written in TOML or YAML, which is easier to read & edit than the json used in the server.
+//! Fields are same as `Rule`, except the following:
+//!
+//! - Cards are written as pattern + number: `"SA"`, `"H10"`, `"DQ"`, `"C2"`, or `"JK-B"`, `"JK-R"`.
+//!   Patterns are `S`, `D`, `H`, `C` and numbers are `2` to `10`, `J`, `Q`, `K`, `A`.
+//!   Suit symbols such as `"♠A"` are also accepted. See `Card`'s `FromStr`.
+//! - `deck` is the list of cards. Same card can be written several times.
+//! - `pattern_order` is the list of patterns.
+//! - `election`, `friend` and `visibility` are the list of flag names in lowercase.
+//! - `friend_reveal`, `tie_break`, `table`, `mighty`, `kitty` and `scoring` can be omitted for the default value.
+//!
+//! ```toml
+//! user_cnt = 5
+//! card_cnt_per_user = 10
+//! deck = ["S2", "S3", "...", "SA", "D2", "...", "CA", "JK-B"]
+//! election = ["increasing", "ordered", "pass_first", "no_giruda_exist"]
+//! friend = ["card", "pick", "first", "last", "fake", "none"]
+//! friend_reveal = "OnPlay"
+//! pattern_order = ["S", "D", "H", "C"]
+//! visibility = ["friend"]
+//! next_dealer = "Friend"
+//!
+//! [missed_deal]
+//! score = 1
+//! joker = 0
+//! limit = 0
+//! declare = "Auto"
+//! redeal = "SameDealer"
+//!
+//! [missed_deal.card]
+//! SA = -1
+//!
+//! [pledge]
+//! min = 13
+//! max = 20
+//! no_giruda_offset = -1
+//! change_cost = 2
+//! first_offset = 0
+//!
+//! [card_policy]
+//! mighty = ["Valid", "Valid"]
+//! giruda = ["Invalid", "Valid"]
+//! joker = ["NoEffect", "NoEffect"]
+//! joker_call = ["Valid", "Valid"]
+//!
+//! [card_policy.card]
+//!
+//! [joker_call]
+//! cards = [["C3", "S3"]]
+//! mighty_defense = true
+//! has_power = false
+//!
+//! [mighty]
+//! card = "SA"
+//! alter = "DA"
+//! enabled = true
+//!
+//! [timing]
+//! election_one_turn = 0
+//! election_total = 0
+//! selectfriend_time = 0
+//! ingame_one_turn = 0
+//! ingame_total = 0
+//! ```
+
+use crate::card::{Card, Color, Pattern};
+use crate::rule::card_policy::{CardPolicy, Policy};
+use crate::rule::dealer::Dealer;
+use crate::rule::deck::{Deck, TieBreak};
+use crate::rule::election::Election;
+use crate::rule::friend::{Friend, FriendReveal};
+use crate::rule::joker_call::JokerCall;
+use crate::rule::kitty::Kitty;
+use crate::rule::mighty::MightyRule;
+use crate::rule::missed_deal::{Declare, MissedDeal, Redeal};
+use crate::rule::pledge::Pledge;
+use crate::rule::scoring::Scoring;
+use crate::rule::table::Table;
+use crate::rule::timing::Timing;
+use crate::rule::visibility::Visibility;
+use crate::rule::Rule;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::BitOr;
+use std::str::FromStr;
+
+/// Format of the rule file
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Toml,
+    #[serde(alias = "yml")]
+    Yaml,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Toml => "toml",
+            Format::Yaml => "yaml",
+        }
+    }
+
+    pub fn mime(&self) -> &'static str {
+        match self {
+            Format::Toml => "application/toml",
+            Format::Yaml => "application/x-yaml",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = FormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "toml" => Ok(Format::Toml),
+            "yaml" | "yml" => Ok(Format::Yaml),
+            _ => Err(FormatError::Format(s.to_owned())),
+        }
+    }
+}
+
+/// Error while reading or writing the rule file
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatError {
+    /// Unknown format name
+    Format(String),
+    /// Error from the parser or the writer
+    Syntax(String),
+}
+
+impl std::error::Error for FormatError {}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::Format(s) => write!(f, "unknown rule format: {}", s),
+            FormatError::Syntax(s) => write!(f, "invalid rule file: {}", s),
+        }
+    }
+}
+
+const ELECTION: &[(&str, Election)] = &[
+    ("increasing", Election::INCREASING),
+    ("ordered", Election::ORDERED),
+    ("pass_first", Election::PASS_FIRST),
+    ("no_giruda_exist", Election::NO_GIRUDA_EXIST),
+];
+
+const FRIEND: &[(&str, Friend)] = &[
+    ("card", Friend::CARD),
+    ("pick", Friend::PICK),
+    ("first", Friend::FIRST),
+    ("last", Friend::LAST),
+    ("fake", Friend::FAKE),
+    ("none", Friend::NONE),
+];
+
+const VISIBILITY: &[(&str, Visibility)] = &[
+    ("president", Visibility::PRESIDENT),
+    ("friend", Visibility::FRIEND),
+    ("other", Visibility::OTHER),
+];
+
+fn flag_names<T: Copy>(flags: T, table: &[(&str, T)], contains: impl Fn(T, T) -> bool) -> Vec<String> {
+    table
+        .iter()
+        .filter(|(_, f)| contains(flags, *f))
+        .map(|(n, _)| (*n).to_owned())
+        .collect()
+}
+
+fn flags_from_names<T: Copy + BitOr<Output = T>>(names: &[String], table: &[(&str, T)], empty: T) -> Result<T, String> {
+    names.iter().try_fold(empty, |flags, n| {
+        table
+            .iter()
+            .find(|(t, _)| t.eq_ignore_ascii_case(n))
+            .map(|(_, f)| flags | *f)
+            .ok_or_else(|| format!("unknown flag: {}", n))
+    })
+}
+
+/// Card written in readable notation
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+struct CardName(Card);
+
+impl Serialize for CardName {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for CardName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map(CardName).map_err(D::Error::custom)
+    }
+}
+
+// Plain values come before tables, since toml can't write values after tables.
+#[derive(Debug, Serialize, Deserialize)]
+struct RuleFile {
+    user_cnt: u8,
+    card_cnt_per_user: u8,
+    deck: Vec<CardName>,
+    election: Vec<String>,
+    friend: Vec<String>,
+    #[serde(default)]
+    friend_reveal: FriendReveal,
+    #[serde(default, skip_serializing_if = "TieBreak::is_default")]
+    tie_break: TieBreak,
+    pattern_order: Vec<String>,
+    visibility: Vec<String>,
+    next_dealer: Dealer,
+    missed_deal: MissedDealFile,
+    pledge: Pledge,
+    card_policy: PolicyFile,
+    joker_call: JokerCallFile,
+    #[serde(default, skip_serializing_if = "Table::is_default")]
+    table: Table,
+    #[serde(default)]
+    mighty: Option<MightyFile>,
+    #[serde(default, skip_serializing_if = "Kitty::is_default")]
+    kitty: Kitty,
+    #[serde(default, skip_serializing_if = "Scoring::is_default")]
+    scoring: Scoring,
+    timing: Timing,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MissedDealFile {
+    score: i8,
+    joker: i8,
+    limit: i8,
+    #[serde(default)]
+    declare: Declare,
+    #[serde(default)]
+    redeal: Redeal,
+    #[serde(default)]
+    card: BTreeMap<CardName, i8>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PolicyFile {
+    mighty: (CardPolicy, CardPolicy),
+    giruda: (CardPolicy, CardPolicy),
+    joker: (CardPolicy, CardPolicy),
+    joker_call: (CardPolicy, CardPolicy),
+    #[serde(default)]
+    card: BTreeMap<CardName, (CardPolicy, CardPolicy)>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JokerCallFile {
+    cards: Vec<(CardName, CardName)>,
+    mighty_defense: bool,
+    has_power: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MightyFile {
+    card: CardName,
+    alter: CardName,
+    enabled: bool,
+}
+
+impl From<&Rule> for RuleFile {
+    fn from(rule: &Rule) -> Self {
+        RuleFile {
+            user_cnt: rule.user_cnt,
+            card_cnt_per_user: rule.card_cnt_per_user,
+            deck: rule.deck.0.iter().map(|c| CardName(*c)).collect(),
+            election: flag_names(rule.election, ELECTION, |x, f| x.contains(f)),
+            friend: flag_names(rule.friend, FRIEND, |x, f| x.contains(f)),
+            friend_reveal: rule.friend_reveal,
+            tie_break: rule.tie_break,
+            pattern_order: rule.pattern_order.iter().map(|p| p.to_string()).collect(),
+            visibility: flag_names(rule.visibility, VISIBILITY, |x, f| x.contains(f)),
+            next_dealer: rule.next_dealer,
+            missed_deal: MissedDealFile {
+                score: rule.missed_deal.score,
+                joker: rule.missed_deal.joker,
+                limit: rule.missed_deal.limit,
+                declare: rule.missed_deal.declare,
+                redeal: rule.missed_deal.redeal,
+                card: rule.missed_deal.card.iter().map(|(c, v)| (CardName(*c), *v)).collect(),
+            },
+            pledge: rule.pledge.clone(),
+            card_policy: PolicyFile {
+                mighty: rule.card_policy.mighty,
+                giruda: rule.card_policy.giruda,
+                joker: rule.card_policy.joker,
+                joker_call: rule.card_policy.joker_call,
+                card: rule.card_policy.card.iter().map(|(c, v)| (CardName(*c), *v)).collect(),
+            },
+            joker_call: JokerCallFile {
+                cards: rule
+                    .joker_call
+                    .cards
+                    .iter()
+                    .map(|(a, b)| (CardName(*a), CardName(*b)))
+                    .collect(),
+                mighty_defense: rule.joker_call.mighty_defense,
+                has_power: rule.joker_call.has_power,
+            },
+            table: rule.table.clone(),
+            mighty: if rule.mighty.is_default() {
+                None
+            } else {
+                Some(MightyFile {
+                    card: CardName(rule.mighty.card),
+                    alter: CardName(rule.mighty.alter),
+                    enabled: rule.mighty.enabled,
+                })
+            },
+            kitty: rule.kitty.clone(),
+            scoring: rule.scoring.clone(),
+            timing: rule.timing.clone(),
+        }
+    }
+}
+
+impl RuleFile {
+    fn into_rule(self) -> Result<Rule, String> {
+        let deck = self.deck.into_iter().map(|c| c.0).collect::<Vec<_>>();
+        let jokers = deck.iter().fold(0, |x, c| match c {
+            Card::Joker(Color::Black) => x | 0b10,
+            Card::Joker(Color::Red) => x | 0b01,
+            _ => x,
+        });
+        Ok(Rule {
+            user_cnt: self.user_cnt,
+            card_cnt_per_user: self.card_cnt_per_user,
+            table: self.table,
+            deck: Deck(deck, jokers),
+            tie_break: self.tie_break,
+            missed_deal: MissedDeal {
+                score: self.missed_deal.score,
+                joker: self.missed_deal.joker,
+                card: self.missed_deal.card.into_iter().map(|(c, v)| (c.0, v)).collect(),
+                limit: self.missed_deal.limit,
+                declare: self.missed_deal.declare,
+                redeal: self.missed_deal.redeal,
+            },
+            election: flags_from_names(&self.election, ELECTION, Election::empty())?,
+            pledge: self.pledge,
+            friend: flags_from_names(&self.friend, FRIEND, Friend::empty())?,
+            friend_reveal: self.friend_reveal,
+            card_policy: Policy {
+                mighty: self.card_policy.mighty,
+                giruda: self.card_policy.giruda,
+                joker: self.card_policy.joker,
+                joker_call: self.card_policy.joker_call,
+                card: self.card_policy.card.into_iter().map(|(c, v)| (c.0, v)).collect(),
+            },
+            joker_call: JokerCall {
+                cards: self.joker_call.cards.into_iter().map(|(a, b)| (a.0, b.0)).collect(),
+                mighty_defense: self.joker_call.mighty_defense,
+                has_power: self.joker_call.has_power,
+            },
+            mighty: self.mighty.map_or_else(MightyRule::new, |m| MightyRule {
+                card: m.card.0,
+                alter: m.alter.0,
+                enabled: m.enabled,
+            }),
+            kitty: self.kitty,
+            scoring: self.scoring,
+            pattern_order: self
+                .pattern_order
+                .iter()
+                .map(|p| p.parse::<Pattern>().map_err(|e| e.to_string()))
+                .collect::<Result<_, _>>()?,
+            visibility: flags_from_names(&self.visibility, VISIBILITY, Visibility::empty())?,
+            next_dealer: self.next_dealer,
+            timing: self.timing,
+        })
+    }
+}
+
+impl Rule {
+    /// Reads the rule from the rule file.
+    /// The rule is not validated, so call `Rule::validate` before using it.
+    pub fn import(s: &str, format: Format) -> Result<Rule, FormatError> {
+        let file: RuleFile = match format {
+            Format::Toml => toml::from_str(s).map_err(|e| FormatError::Syntax(e.to_string()))?,
+            Format::Yaml => serde_yaml::from_str(s).map_err(|e| FormatError::Syntax(e.to_string()))?,
+        };
+        file.into_rule().map_err(FormatError::Syntax)
+    }
+
+    /// Writes the rule as the rule file.
+    pub fn export(&self, format: Format) -> Result<String, FormatError> {
+        let file = RuleFile::from(self);
+        match format {
+            Format::Toml => toml::to_string(&file).map_err(|e| FormatError::Syntax(e.to_string())),
+            Format::Yaml => serde_yaml::to_string(&file).map_err(|e| FormatError::Syntax(e.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rule::Preset;
+
+    #[test]
+    fn round_trip_test() {
+        let presets = [
+            Preset::Default5,
+            Preset::Ddshs5,
+            Preset::Dhsh5,
+            Preset::Kmla5,
+            Preset::Gsa5,
+            Preset::Gshs5,
+            Preset::Skku5,
+            Preset::Sshs5,
+            Preset::Yu5,
+            Preset::Double10,
+            Preset::Practice3,
+            Preset::Practice2,
+        ];
+        for preset in presets.iter() {
+            let mut rule = Rule::from(*preset);
+            rule.missed_deal.card.insert(Card::Normal(Pattern::Spade, 14), -1);
+            rule.kitty.max_exchange = Some(1);
+            rule.scoring.base = 8;
+            for format in [Format::Toml, Format::Yaml].iter() {
+                let s = rule.export(*format).unwrap();
+                assert_eq!(
+                    Rule::import(&s, *format),
+                    Ok(rule.clone()),
+                    "{:?} {:?}\n{}",
+                    preset,
+                    format,
+                    s
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn import_test() {
+        let mut rule = Rule::new();
+        rule.mighty.card = Card::Normal(Pattern::Heart, 14);
+        let s = rule.export(Format::Toml).unwrap();
+        assert!(s.contains("\"JK-B\""));
+        assert!(s.contains("card = \"HA\""));
+
+        let unicode = s.replace("\"HA\"", "\"♥A\"");
+        assert_eq!(Rule::import(&unicode, Format::Toml), Ok(rule));
+
+        let s = s.replace("\"HA\"", "\"HZ\"");
+        assert!(matches!(Rule::import(&s, Format::Toml), Err(FormatError::Syntax(_))));
+        assert!(Rule::import("user_cnt: 5", Format::Yaml).is_err());
+        assert_eq!("yml".parse(), Ok(Format::Yaml));
+        assert!("json".parse::<Format>().is_err());
+    }
+}
@@ -12,3 +12,22 @@ bitflags! {
         const NONE  = 0b100000;
     }
 }
+
+/// When the friend becomes publicly known
+///
+/// - `OnPlay`: as soon as the friend is determined, e.g. when the friend card is played
+/// - `FirstTrick`: at the end of the first trick, or when determined if it is later
+/// - `End`: only when the game is ended
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Hash, Eq, PartialEq, Default)]
+pub enum FriendReveal {
+    #[default]
+    OnPlay,
+    FirstTrick,
+    End,
+}
+
+impl FriendReveal {
+    pub fn is_default(&self) -> bool {
+        *self == FriendReveal::OnPlay
+    }
+}
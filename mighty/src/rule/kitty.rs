@@ -0,0 +1,61 @@
+use config::Config;
+use serde::{Deserialize, Serialize};
+
+/// Rule of the kitty, the cards left after dealing which are taken by the president
+///
+/// - `reveal`: who can see the kitty
+/// - `points`: who gets the points of the cards dropped by the president
+/// - `max_exchange`: the most cards of the kitty the president can keep, `None` for no limit
+///
+/// Size of the kitty is the count of cards left after dealing, see `Rule::kitty_size`.
+#[derive(Debug, Clone, Serialize, Deserialize, Config, Hash, Eq, PartialEq)]
+pub struct Kitty {
+    pub reveal: KittyReveal,
+    pub points: KittyPoints,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_exchange: Option<u8>,
+}
+
+impl Default for Kitty {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Kitty {
+    pub fn new() -> Kitty {
+        Kitty {
+            reveal: KittyReveal::President,
+            points: KittyPoints::Ruling,
+            max_exchange: None,
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Kitty::new()
+    }
+}
+
+/// Who can see the kitty
+///
+/// - `President`: only the president
+/// - `All`: everyone, while the president selects the friend
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Hash, Eq, PartialEq, Default)]
+pub enum KittyReveal {
+    #[default]
+    President,
+    All,
+}
+
+/// Who gets the points of the cards dropped by the president
+///
+/// - `Ruling`: the ruling party (president and friend)
+/// - `Opposition`: the opposition
+/// - `Nobody`: no one
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Hash, Eq, PartialEq, Default)]
+pub enum KittyPoints {
+    #[default]
+    Ruling,
+    Opposition,
+    Nobody,
+}
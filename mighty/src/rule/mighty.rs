@@ -0,0 +1,46 @@
+use crate::card::{Card, Pattern};
+use config::Config;
+use serde::{Deserialize, Serialize};
+
+/// Rule of the mighty card
+///
+/// - `card`: the mighty card
+/// - `alter`: the mighty card when the pattern of `card` is giruda
+/// - `enabled`: if `false`, there is no mighty in the game
+#[derive(Debug, Clone, Serialize, Deserialize, Config, Hash, Eq, PartialEq)]
+pub struct MightyRule {
+    pub card: Card,
+    pub alter: Card,
+    pub enabled: bool,
+}
+
+impl Default for MightyRule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MightyRule {
+    pub fn new() -> MightyRule {
+        MightyRule {
+            card: Card::Normal(Pattern::Spade, 14),
+            alter: Card::Normal(Pattern::Diamond, 14),
+            enabled: true,
+        }
+    }
+
+    /// Mighty card of the game with `giruda`
+    pub fn get(&self, giruda: Option<Pattern>) -> Option<Card> {
+        if !self.enabled {
+            return None;
+        }
+        match self.card {
+            Card::Normal(p, _) if Some(p) == giruda => Some(self.alter),
+            _ => Some(self.card),
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == MightyRule::new()
+    }
+}
@@ -4,17 +4,60 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 
+/// How the missed deal is handled
+///
+/// - `Auto`: reshuffled silently before the election
+/// - `Voluntary`: the holder can declare it before anyone pledges
+/// - `Forced`: always declared and revealed to the table, then dealt again
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Hash, Eq, PartialEq, Default)]
+pub enum Declare {
+    #[default]
+    Auto,
+    Voluntary,
+    Forced,
+}
+
+/// Who deals again after the missed deal is declared
+///
+/// - `SameDealer`: the dealer of the missed deal
+/// - `Declarer`: the user who declared the missed deal
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Hash, Eq, PartialEq, Default)]
+pub enum Redeal {
+    #[default]
+    SameDealer,
+    Declarer,
+}
+
+impl Redeal {
+    pub fn dealer(&self, dealer: usize, declarer: usize) -> usize {
+        match self {
+            Redeal::SameDealer => dealer,
+            Redeal::Declarer => declarer,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Config, Eq)]
 pub struct MissedDeal {
     pub score: i8,
     pub joker: i8,
+    #[serde(with = "crate::card::card_map")]
     pub card: HashMap<Card, i8>,
     pub limit: i8,
+    #[serde(default)]
+    pub declare: Declare,
+    #[serde(default)]
+    pub redeal: Redeal,
 }
 
 impl PartialEq for MissedDeal {
     fn eq(&self, other: &Self) -> bool {
-        self.score == other.score && self.joker == other.joker && self.card == other.card && self.limit == other.limit
+        self.score == other.score
+            && self.joker == other.joker
+            && self.card == other.card
+            && self.limit == other.limit
+            && self.declare == other.declare
+            && self.redeal == other.redeal
     }
 }
 
@@ -23,6 +66,8 @@ impl Hash for MissedDeal {
         self.score.hash(state);
         self.joker.hash(state);
         self.limit.hash(state);
+        self.declare.hash(state);
+        self.redeal.hash(state);
 
         for (k, v) in self.card.iter() {
             k.hash(state);
@@ -44,6 +89,8 @@ impl MissedDeal {
             joker: 0,
             card: HashMap::new(),
             limit: 0,
+            declare: Declare::Auto,
+            redeal: Redeal::SameDealer,
         }
     }
 
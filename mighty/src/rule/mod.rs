@@ -2,36 +2,53 @@ pub mod card_policy;
 pub mod dealer;
 pub mod deck;
 pub mod election;
+#[cfg(feature = "format")]
+pub mod format;
 pub mod friend;
 pub mod joker_call;
+pub mod kitty;
+pub mod mighty;
 pub mod missed_deal;
 pub mod pledge;
+pub mod scoring;
+pub mod table;
 pub mod timing;
 pub mod visibility;
 
 pub mod prelude {
     pub use crate::rule::card_policy::{CardPolicy, Policy};
     pub use crate::rule::dealer::Dealer;
-    pub use crate::rule::deck::{DeckBuilder, Preset as DeckPreset};
+    pub use crate::rule::deck::{DeckBuilder, Preset as DeckPreset, TieBreak};
     pub use crate::rule::election::Election;
-    pub use crate::rule::friend::Friend;
+    #[cfg(feature = "format")]
+    pub use crate::rule::format::{Format, FormatError};
+    pub use crate::rule::friend::{Friend, FriendReveal};
     pub use crate::rule::joker_call::JokerCall;
-    pub use crate::rule::missed_deal::MissedDeal;
+    pub use crate::rule::kitty::{Kitty, KittyPoints, KittyReveal};
+    pub use crate::rule::mighty::MightyRule;
+    pub use crate::rule::missed_deal::{Declare, MissedDeal, Redeal};
     pub use crate::rule::pledge::Pledge;
+    pub use crate::rule::scoring::{Loss, Multiplier, Scoring};
+    pub use crate::rule::table::Table;
     pub use crate::rule::visibility::Visibility;
 
     pub use crate::rule::{Preset, Rule};
 }
 
 use crate::card::{Card, Pattern};
+use crate::error::RuleError;
 use crate::rule::card_policy::{CardPolicy, Policy};
 use crate::rule::dealer::Dealer;
-use crate::rule::deck::Deck;
+use crate::rule::deck::{Deck, TieBreak};
 use crate::rule::election::Election;
-use crate::rule::friend::Friend;
+use crate::rule::friend::{Friend, FriendReveal};
 use crate::rule::joker_call::JokerCall;
-use crate::rule::missed_deal::MissedDeal;
+use crate::rule::kitty::Kitty;
+use crate::rule::mighty::MightyRule;
+use crate::rule::missed_deal::{Declare, MissedDeal};
 use crate::rule::pledge::Pledge;
+use crate::rule::scoring::{Loss, Scoring};
+use crate::rule::table::Table;
 use crate::rule::timing::Timing;
 use crate::rule::visibility::Visibility;
 use config::Config;
@@ -60,24 +77,44 @@ pub enum Preset {
     Sshs5,
     // 연세대학교 5마
     Yu5,
+    // 더블덱 10마
+    Double10,
+    // 3마 연습 (더미 1개)
+    Practice3,
+    // 2마 연습 (패 공개)
+    Practice2,
 }
 
 /// Rule in mighty game
 ///
 /// Can make custom rule for regional mighty rules.
 /// Mighty Game is implemented based on this rule.
-/// todo: add mighty customization
+///
+/// Fields added later are skipped in serialization with their `is_default`,
+/// so that the saved rules without them keep the same hash.
 #[derive(Debug, Clone, Serialize, Deserialize, Config, Hash, Eq, PartialEq)]
 pub struct Rule {
     pub user_cnt: u8,
     pub card_cnt_per_user: u8,
+    #[serde(default, skip_serializing_if = "Table::is_default")]
+    pub table: Table,
     pub deck: Deck,
+    #[serde(default, skip_serializing_if = "TieBreak::is_default")]
+    pub tie_break: TieBreak,
     pub missed_deal: MissedDeal,
     pub election: Election,
     pub pledge: Pledge,
     pub friend: Friend,
+    #[serde(default, skip_serializing_if = "FriendReveal::is_default")]
+    pub friend_reveal: FriendReveal,
     pub card_policy: Policy,
     pub joker_call: JokerCall,
+    #[serde(default, skip_serializing_if = "MightyRule::is_default")]
+    pub mighty: MightyRule,
+    #[serde(default, skip_serializing_if = "Kitty::is_default")]
+    pub kitty: Kitty,
+    #[serde(default, skip_serializing_if = "Scoring::is_default")]
+    pub scoring: Scoring,
     pub pattern_order: Vec<Pattern>,
     pub visibility: Visibility,
     pub next_dealer: Dealer,
@@ -103,7 +140,12 @@ impl From<Preset> for Rule {
                 .set_election(Election::all() - Election::PASS_FIRST)
                 .map_card_policy(|p| p.set_mighty((CardPolicy::NoEffect, CardPolicy::Valid))),
             Preset::Kmla5 => Rule::new()
-                .map_missed_deal(|m| m.set_score(1).set_joker(-1).set_limit(1))
+                .map_missed_deal(|m| {
+                    m.set_score(1)
+                        .set_joker(-1)
+                        .set_limit(1)
+                        .set_declare(Declare::Voluntary)
+                })
                 .map_joker_call(|j| j.set_mighty_defense(false)),
             Preset::Gsa5 => Rule::new().map_pledge(|p| p.set_min(12)).map_card_policy(|p| {
                 p.set_mighty((CardPolicy::NoEffect, CardPolicy::Valid))
@@ -119,6 +161,7 @@ impl From<Preset> for Rule {
                             m.insert(Card::Normal(Pattern::Spade, 14), -2);
                         })
                         .set_limit(1)
+                        .set_declare(Declare::Voluntary)
                 })
                 .map_pledge(|p| p.set_min(14))
                 .map_joker_call(|j| {
@@ -133,7 +176,9 @@ impl From<Preset> for Rule {
                     p.set_joker((CardPolicy::Valid, CardPolicy::Valid))
                         .set_giruda((CardPolicy::Valid, CardPolicy::Valid))
                 })
-                .map_joker_call(|j| j.set_has_power(true)),
+                .map_joker_call(|j| j.set_has_power(true))
+                // the run and the back run are tripled, and the loss counts from the pledge
+                .map_scoring(|s| s.set_run(3).set_back_run(3).set_loss(Loss::Pledge)),
             Preset::Sshs5 => Rule::new()
                 .map_missed_deal(|m| {
                     m.set_score(2)
@@ -146,6 +191,7 @@ impl From<Preset> for Rule {
                             m.insert(Card::Normal(Pattern::Spade, 14), 1);
                         })
                         .set_limit(1)
+                        .set_declare(Declare::Voluntary)
                 })
                 .set_friend(Friend::all() - Friend::PICK)
                 .map_card_policy(|p| p.set_joker_call((CardPolicy::NoEffect, CardPolicy::Valid))),
@@ -158,10 +204,32 @@ impl From<Preset> for Rule {
                             m.insert(Card::Normal(Pattern::Spade, 14), 1);
                         })
                         .set_limit(1)
+                        .set_declare(Declare::Voluntary)
                 })
                 .set_election(Election::INCREASING | Election::ORDERED)
                 .map_pledge(|p| p.set_min(14).set_max(23))
-                .map_card_policy(|p| p.set_joker_call((CardPolicy::NoEffect, CardPolicy::Valid))),
+                .map_card_policy(|p| p.set_joker_call((CardPolicy::NoEffect, CardPolicy::Valid)))
+                // score counts from 12 points, and the friend shares it equally with the president
+                .map_scoring(|s| s.set_base(12).set_share((1, 1, 1))),
+            Preset::Double10 => Rule::new()
+                .set_user_cnt(10)
+                .set_deck(deck::Preset::DoubleDeck.build())
+                .map_pledge(|p| p.set_min(26).set_max(40))
+                .map_scoring(|s| s.set_base(20))
+                .map_joker_call(|j| {
+                    j.mut_cards(|v| {
+                        v.push((Card::Normal(Pattern::Heart, 3), Card::Normal(Pattern::Diamond, 3)));
+                    })
+                }),
+            Preset::Practice3 => Rule::new()
+                .set_user_cnt(3)
+                .set_card_cnt_per_user(12)
+                .map_table(|t| t.set_dummy(1)),
+            Preset::Practice2 => Rule::new()
+                .set_user_cnt(2)
+                .set_card_cnt_per_user(25)
+                .map_table(|t| t.set_open_hand(true))
+                .set_friend(Friend::NONE),
         }
     }
 }
@@ -177,13 +245,19 @@ impl Rule {
         Rule {
             user_cnt: 5,
             card_cnt_per_user: 10,
+            table: Table::new(),
             deck: deck::Preset::SingleJoker.build(),
+            tie_break: TieBreak::First,
             missed_deal: MissedDeal::new(),
             election: Election::all(),
             pledge: Pledge::new(),
             friend: Friend::all(),
+            friend_reveal: FriendReveal::OnPlay,
             card_policy: Policy::new(),
             joker_call: JokerCall::new(),
+            mighty: MightyRule::new(),
+            kitty: Kitty::new(),
+            scoring: Scoring::new(),
             pattern_order: vec![Pattern::Spade, Pattern::Diamond, Pattern::Heart, Pattern::Clover],
             visibility: Visibility::FRIEND,
             next_dealer: Dealer::Friend,
@@ -191,17 +265,165 @@ impl Rule {
         }
     }
 
-    pub fn valid(&self) -> bool {
-        self.user_cnt > 0
-            && self.user_cnt <= 8
-            && self.card_cnt_per_user > 0
-            && self.user_cnt * self.card_cnt_per_user <= self.deck.0.len() as u8
-            && self.pledge.valid()
-            && self.deck.0.iter().filter(|c| c.is_joker()).count() == self.joker_call.len()
-            && {
-                let mut v = self.pattern_order.clone();
-                v.sort();
-                v == vec![Pattern::Spade, Pattern::Diamond, Pattern::Heart, Pattern::Clover]
-            }
+    /// Count of hands dealt, the users and the dummy hands after them
+    pub fn seat_cnt(&self) -> usize {
+        self.user_cnt as usize + self.table.dummy as usize
+    }
+
+    /// Whether the seat is a dummy hand, which is played by the president
+    pub fn is_dummy(&self, seat: usize) -> bool {
+        seat >= self.user_cnt as usize
+    }
+
+    /// Bitmask of the dummy hands, which belong to the ruling party
+    pub fn dummy_seats(&self) -> u16 {
+        ((1u32 << self.seat_cnt()) - (1u32 << self.user_cnt)) as u16
+    }
+
+    /// Turn count of the last trick
+    pub fn last_turn(&self) -> u8 {
+        self.card_cnt_per_user.saturating_sub(1)
+    }
+
+    /// Count of cards left after dealing, which are taken by the president
+    pub fn kitty_size(&self) -> usize {
+        self.deck
+            .0
+            .len()
+            .saturating_sub(self.seat_cnt() * self.card_cnt_per_user as usize)
+    }
+
+    /// Checks every constraint of the rule, and returns all the violated ones.
+    pub fn validate(&self) -> Result<(), Vec<RuleError>> {
+        let mut errors = Vec::new();
+
+        if self.user_cnt == 0 || self.seat_cnt() > 16 {
+            errors.push(RuleError::UserCount(self.user_cnt));
+        }
+        if self.card_cnt_per_user == 0 {
+            errors.push(RuleError::CardCount);
+        }
+        let needed = self.seat_cnt() * self.card_cnt_per_user as usize;
+        if needed > self.deck.0.len() {
+            errors.push(RuleError::DeckSize(needed, self.deck.0.len()));
+        }
+        // one joker call for each color of the joker, even if the deck has duplicate jokers
+        let jokers = self.deck.1.count_ones() as usize;
+        if jokers != self.joker_call.len() {
+            errors.push(RuleError::JokerCall(jokers, self.joker_call.len()));
+        }
+        if self.mighty.enabled && !(self.deck.0.contains(&self.mighty.card) && self.deck.0.contains(&self.mighty.alter))
+        {
+            errors.push(RuleError::Mighty);
+        }
+        let mut patterns = self.pattern_order.clone();
+        patterns.sort();
+        if patterns != vec![Pattern::Spade, Pattern::Diamond, Pattern::Heart, Pattern::Clover] {
+            errors.push(RuleError::PatternOrder);
+        }
+        if self.pledge.min >= self.pledge.max {
+            errors.push(RuleError::PledgeRange(self.pledge.min, self.pledge.max));
+        }
+        let scoring = &self.scoring;
+        if scoring.base >= self.pledge.min
+            || [scoring.no_giruda, scoring.no_friend, scoring.run, scoring.back_run].contains(&0)
+        {
+            errors.push(RuleError::Scoring);
+        }
+        if self.friend.is_empty() || Friend::from_bits(self.friend.bits()).is_none() {
+            errors.push(RuleError::Friend);
+        }
+        if Visibility::from_bits(self.visibility.bits()).is_none() {
+            errors.push(RuleError::Visibility);
+        }
+        let timing = &self.timing;
+        if timing.election_one_turn > 0 && timing.election_total > 0 && timing.election_one_turn > timing.election_total
+        {
+            errors.push(RuleError::Timing("election"));
+        }
+        if timing.ingame_one_turn > 0 && timing.ingame_total > 0 && timing.ingame_one_turn > timing.ingame_total {
+            errors.push(RuleError::Timing("in-game"));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::card::Card;
+    use crate::rule::scoring::Multiplier;
+
+    #[test]
+    fn preset_test() {
+        let presets = [
+            Preset::Default5,
+            Preset::Ddshs5,
+            Preset::Dhsh5,
+            Preset::Kmla5,
+            Preset::Gsa5,
+            Preset::Gshs5,
+            Preset::Skku5,
+            Preset::Sshs5,
+            Preset::Yu5,
+            Preset::Double10,
+            Preset::Practice3,
+            Preset::Practice2,
+        ];
+        for preset in presets.iter() {
+            let rule = Rule::from(*preset);
+            assert_eq!(rule.validate(), Ok(()), "{:?}", preset);
+            let json = serde_json::to_string(&rule).unwrap();
+            assert_eq!(serde_json::from_str::<Rule>(&json).unwrap(), rule, "{:?}", preset);
+        }
+
+        let skku5 = Rule::from(Preset::Skku5).scoring;
+        assert_eq!((skku5.base, skku5.run, skku5.back_run), (10, 3, 3));
+        assert_eq!(skku5.loss, Loss::Pledge);
+        assert_eq!(
+            skku5.game_score(14, 20, true, false, false),
+            (true, 30, vec![Multiplier::Run(3)])
+        );
+        assert_eq!(skku5.game_score(16, 13, false, false, false), (false, 6, vec![]));
+        let yu5 = Rule::from(Preset::Yu5).scoring;
+        assert_eq!((yu5.base, yu5.share, yu5.loss), (12, (1, 1, 1), Loss::Missing));
+        assert_eq!(yu5.game_score(15, 16, false, false, false), (true, 4, vec![]));
+        assert_eq!(
+            yu5.game_score(15, 11, false, false, false),
+            (false, 8, vec![Multiplier::BackRun(2)])
+        );
+    }
+
+    #[test]
+    fn validate_test() {
+        let mut rule = Rule::new();
+        rule.user_cnt = 6;
+        rule.deck.0.retain(|c| *c != Card::Normal(Pattern::Spade, 14));
+        rule.pattern_order.pop();
+        rule.pledge.min = 21;
+        rule.pledge.max = 21;
+        rule.friend = Friend::empty();
+        rule.timing.ingame_one_turn = 30;
+        rule.timing.ingame_total = 10;
+
+        let errors = rule.validate().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                RuleError::DeckSize(60, 52),
+                RuleError::Mighty,
+                RuleError::PatternOrder,
+                RuleError::PledgeRange(21, 21),
+                RuleError::Friend,
+                RuleError::Timing("in-game"),
+            ]
+        );
+        assert_eq!(errors[0].code(), "deck_size");
+        assert_eq!(errors[0].to_string(), "60 cards are needed, but the deck has 52");
     }
 }
@@ -26,8 +26,4 @@ impl Pledge {
             first_offset: 0,
         }
     }
-
-    pub fn valid(&self) -> bool {
-        self.min < self.max
-    }
 }
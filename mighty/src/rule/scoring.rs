@@ -0,0 +1,154 @@
+use config::Config;
+use serde::{Deserialize, Serialize};
+
+/// Rule of the score when the game ends
+///
+/// - `base`: the ruling party wins `points - base` before multipliers
+/// - `no_giruda`, `no_friend`, `run`: multipliers of winning without giruda, without friend,
+///   and with every point (opposition has no point)
+/// - `back_run`: multiplier of losing with `base` points or less
+/// - `loss`: formula of the score when the ruling party loses
+/// - `share`: weights of each player's score, `(president, friend, opposition)`
+///
+/// Winners get the score times their weight, and losers lose it.
+#[derive(Debug, Clone, Serialize, Deserialize, Config, Hash, Eq, PartialEq)]
+pub struct Scoring {
+    pub base: u8,
+    pub no_giruda: u8,
+    pub no_friend: u8,
+    pub run: u8,
+    pub back_run: u8,
+    pub loss: Loss,
+    pub share: (u8, u8, u8),
+}
+
+/// Score when the ruling party loses, before `back_run` multiplier
+///
+/// - `Missing`: the missing points, `pledge - points`
+/// - `Pledge`: the score of winning with the pledge, `pledge - base`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum Loss {
+    Missing,
+    Pledge,
+}
+
+/// Multiplier applied to the score, with its factor
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum Multiplier {
+    NoGiruda(u8),
+    NoFriend(u8),
+    Run(u8),
+    BackRun(u8),
+}
+
+impl Multiplier {
+    pub fn factor(&self) -> u8 {
+        match self {
+            Multiplier::NoGiruda(x) | Multiplier::NoFriend(x) | Multiplier::Run(x) | Multiplier::BackRun(x) => *x,
+        }
+    }
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scoring {
+    pub fn new() -> Scoring {
+        Scoring {
+            base: 10,
+            no_giruda: 2,
+            no_friend: 2,
+            run: 2,
+            back_run: 2,
+            loss: Loss::Missing,
+            share: (2, 1, 1),
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Scoring::new()
+    }
+
+    /// Whether the ruling party wins, the score of the game, and the multipliers applied to it.
+    ///
+    /// `points` is the points of the ruling party, and `run` is whether the opposition has no point.
+    pub fn game_score(
+        &self,
+        pledge: u8,
+        points: u8,
+        run: bool,
+        no_giruda: bool,
+        no_friend: bool,
+    ) -> (bool, i32, Vec<Multiplier>) {
+        let (is_win, score, multipliers) = if points >= pledge {
+            let multipliers = [
+                (no_giruda, Multiplier::NoGiruda(self.no_giruda)),
+                (no_friend, Multiplier::NoFriend(self.no_friend)),
+                (run, Multiplier::Run(self.run)),
+            ]
+            .iter()
+            .filter(|(apply, _)| *apply)
+            .map(|(_, m)| *m)
+            .collect::<Vec<_>>();
+            (true, points as i32 - self.base as i32, multipliers)
+        } else {
+            let score = match self.loss {
+                Loss::Missing => pledge as i32 - points as i32,
+                Loss::Pledge => pledge as i32 - self.base as i32,
+            };
+            let multipliers = if points <= self.base {
+                vec![Multiplier::BackRun(self.back_run)]
+            } else {
+                Vec::new()
+            };
+            (false, score, multipliers)
+        };
+        let score = multipliers.iter().fold(score, |s, m| s * m.factor() as i32);
+        (is_win, score, multipliers)
+    }
+
+    /// Weight of the user's score
+    pub fn share_of(&self, user: usize, president: usize, friend: Option<usize>) -> i32 {
+        if user == president {
+            self.share.0 as i32
+        } else if Some(user) == friend {
+            self.share.1 as i32
+        } else {
+            self.share.2 as i32
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn game_score_test() {
+        let scoring = Scoring::new();
+        assert_eq!(scoring.game_score(13, 15, false, false, false), (true, 5, vec![]));
+        assert_eq!(
+            scoring.game_score(13, 20, true, true, true),
+            (
+                true,
+                80,
+                vec![Multiplier::NoGiruda(2), Multiplier::NoFriend(2), Multiplier::Run(2)]
+            )
+        );
+        assert_eq!(scoring.game_score(15, 12, false, false, false), (false, 3, vec![]));
+        assert_eq!(
+            scoring.game_score(15, 8, false, true, false),
+            (false, 14, vec![Multiplier::BackRun(2)])
+        );
+
+        let scoring = scoring.set_loss(Loss::Pledge).set_base(8);
+        assert_eq!(scoring.game_score(15, 12, false, false, false), (false, 7, vec![]));
+        assert_eq!(
+            scoring.game_score(15, 15, false, true, false),
+            (true, 14, vec![Multiplier::NoGiruda(2)])
+        );
+    }
+}
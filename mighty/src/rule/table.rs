@@ -0,0 +1,34 @@
+use config::Config;
+use serde::{Deserialize, Serialize};
+
+/// Rule of the table for small games, like practice with few users
+///
+/// - `dummy`: count of dummy hands, which sit after the users and don't take part in the election.
+///   The president plays them in their turn, and they are visible to everyone.
+/// - `open_hand`: every hand is visible to everyone, including the president's hand with the kitty
+///
+/// Dummy hands are dealt and take tricks like users, see `Rule::seat_cnt`.
+#[derive(Debug, Clone, Serialize, Deserialize, Config, Hash, Eq, PartialEq)]
+pub struct Table {
+    pub dummy: u8,
+    pub open_hand: bool,
+}
+
+impl Default for Table {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Table {
+    pub fn new() -> Table {
+        Table {
+            dummy: 0,
+            open_hand: false,
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Table::new()
+    }
+}
@@ -1,13 +1,20 @@
 use crate::card::{Card, Pattern, Rush};
-use crate::rule::{card_policy::CardPolicy, election, Rule};
+use crate::rule::{
+    card_policy::CardPolicy, election, kitty::KittyReveal, missed_deal::Declare, scoring::Multiplier, Rule,
+};
 use serde::{Deserialize, Serialize};
+use std::fmt;
 #[cfg(feature = "server")]
 use {
     crate::card::Color,
     crate::command::Command,
     crate::error::{Error, Result},
-    crate::rule::friend,
-    rand::seq::SliceRandom,
+    crate::rule::deck::TieBreak,
+    crate::rule::friend::{self, FriendReveal},
+    crate::rule::kitty::KittyPoints,
+    rand::rngs::StdRng,
+    rand::seq::{IteratorRandom, SliceRandom},
+    rand::{Rng, SeedableRng},
     std::cmp::Ordering,
 };
 /*#[cfg(any(feature = "client", feature = "server"))]
@@ -27,6 +34,41 @@ pub enum FriendFunc {
     Last,
 }
 
+/// Phase of the game, one for each variant of `State`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum Phase {
+    Election,
+    SelectFriend,
+    InGame,
+    GameEnded,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Phase::Election => write!(f, "the election"),
+            Phase::SelectFriend => write!(f, "selecting the friend"),
+            Phase::InGame => write!(f, "the game"),
+            Phase::GameEnded => write!(f, "the end of the game"),
+        }
+    }
+}
+
+/// Finished trick of the game
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct Trick {
+    // user who led the trick
+    pub leader: usize,
+    // placed cards in play order from the leader
+    pub cards: Vec<(Card, CardPolicy)>,
+    // pattern of the trick, declared by the leader
+    pub rush: Rush,
+    // if the joker is called by the leader
+    pub joker_call: bool,
+    // user who took the trick
+    pub winner: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
 pub enum State {
     Election {
@@ -40,12 +82,18 @@ pub enum State {
         start_user: Option<usize>,
         deck: Vec<Vec<Card>>,
         left: Vec<Card>,
+        // hand revealed by missed deal in the last deal (user, hand)
+        #[serde(default)]
+        revealed: Option<(usize, Vec<Card>)>,
     },
     SelectFriend {
         president: usize,
         giruda: Option<Pattern>,
         pledge: u8,
         deck: Vec<Vec<Card>>,
+        // kitty taken by the president, which is already in the hand
+        #[serde(default)]
+        kitty: Vec<Card>,
     },
     InGame {
         // president in in-game user id
@@ -63,8 +111,14 @@ pub enum State {
         pledge: u8,
         // deck for each user (len of 5)
         deck: Vec<Vec<Card>>,
+        // cards dropped by the president
+        #[serde(default)]
+        dropped: Vec<Card>,
         // score cards
         score_deck: Vec<Vec<Card>>,
+        // finished tricks in order
+        #[serde(default)]
+        tricks: Vec<Trick>,
         // turn count 0 to 9
         turn_count: u8,
         // placed cards in front of users
@@ -80,44 +134,94 @@ pub enum State {
         joker_call_effect: bool,
     },
     GameEnded {
-        // bitmask of winners
+        // bitmask of winning users, without the dummy hands
         // ex) if 0 and 3 win: 0b01001
-        winner: u8,
+        winner: u16,
         // below are game info
         president: usize,
         friend: Option<usize>,
-        score: u8,
+        // score of each user by `Rule::scoring`, negative for losers
+        scores: Vec<i32>,
         pledge: u8,
         giruda: Option<Pattern>,
+        // score cards taken by each user
+        #[serde(default)]
+        score_deck: Vec<Vec<Card>>,
+        // cards dropped by the president
+        #[serde(default)]
+        dropped: Vec<Card>,
+        // points of the ruling party, including dropped cards by `Rule::kitty`
+        #[serde(default)]
+        points: u8,
+        // multipliers applied to the score
+        #[serde(default)]
+        multipliers: Vec<Multiplier>,
+        // tricks of the game in order
+        #[serde(default)]
+        tricks: Vec<Trick>,
     },
 }
 
 impl State {
+    pub fn phase(&self) -> Phase {
+        match self {
+            State::Election { .. } => Phase::Election,
+            State::SelectFriend { .. } => Phase::SelectFriend,
+            State::InGame { .. } => Phase::InGame,
+            State::GameEnded { .. } => Phase::GameEnded,
+        }
+    }
+
+    /// Hands of the users, and the kitty at last
     #[cfg(feature = "server")]
-    fn get_random_deck(rule: &Rule) -> Vec<Vec<Card>> {
+    fn get_random_deck<R: Rng>(rule: &Rule, rng: &mut R) -> Vec<Vec<Card>> {
         loop {
-            let mut deck = rule.deck.0.clone();
-            deck.shuffle(&mut rand::thread_rng());
-            let deck = deck
+            let mut cards = rule.deck.0.clone();
+            cards.shuffle(rng);
+            let kitty = cards.split_off(cards.len() - rule.kitty_size());
+            let mut deck = cards
                 .chunks(rule.card_cnt_per_user as usize)
                 .map(|v| v.to_vec())
                 .collect::<Vec<_>>();
-            let is_not_missed_deal = deck
-                .iter()
-                .map(|v| {
-                    if v.len() == rule.card_cnt_per_user as usize {
-                        !rule.missed_deal.is_missed_deal(&v)
-                    } else {
-                        true
-                    }
-                })
-                .all(|s| s);
-            if is_not_missed_deal {
+            deck.push(kitty);
+            if rule.missed_deal.declare != Declare::Auto || State::missed_deal_user(rule, &deck).is_none() {
                 break deck;
             }
         }
     }
 
+    /// The first user who has missed deal
+    #[cfg(feature = "server")]
+    fn missed_deal_user(rule: &Rule, deck: &[Vec<Card>]) -> Option<usize> {
+        deck.iter()
+            .take(rule.user_cnt as usize)
+            .position(|v| v.len() == rule.card_cnt_per_user as usize && rule.missed_deal.is_missed_deal(v))
+    }
+
+    /// Deals cards and starts the election from the dealer.
+    /// If the missed deal is forced to be declared, it is revealed and dealt again.
+    #[cfg(feature = "server")]
+    fn deal<R: Rng>(rule: &Rule, mut dealer: usize, mut revealed: Option<(usize, Vec<Card>)>, rng: &mut R) -> State {
+        let mut deck = State::get_random_deck(rule, rng);
+        if rule.missed_deal.declare == Declare::Forced {
+            while let Some(user) = State::missed_deal_user(rule, &deck) {
+                revealed = Some((user, deck[user].clone()));
+                dealer = rule.missed_deal.redeal.dealer(dealer, user);
+                deck = State::get_random_deck(rule, rng);
+            }
+        }
+        let left = deck.pop().unwrap();
+        State::Election {
+            pledge: vec![None; rule.user_cnt as usize],
+            done: vec![false; rule.user_cnt as usize],
+            curr_user: dealer,
+            start_user: None,
+            deck,
+            left,
+            revealed,
+        }
+    }
+
     /*
     #[cfg(feature = "server")]
     fn is_joker_called(&self) -> bool {
@@ -146,13 +250,27 @@ impl State {
         }
     }
 
+    /// Cards which call the joker with the giruda, the second card of the pair is used
+    /// when the first card has the giruda pattern
+    #[cfg(feature = "server")]
+    fn joker_calls(rule: &Rule, giruda: Option<Pattern>) -> Vec<Card> {
+        rule.joker_call
+            .cards
+            .iter()
+            .map(|(c, alter)| {
+                if matches!(giruda, Some(y) if Rush::from(y) == Rush::from(*c)) {
+                    *alter
+                } else {
+                    *c
+                }
+            })
+            .collect()
+    }
+
     #[cfg(feature = "server")]
     //#[cfg(any(feature = "client", feature = "server"))]
-    fn get_mighty(&self) -> Card {
-        match self.get_giruda() {
-            Some(Pattern::Spade) => Card::Normal(Pattern::Diamond, 14),
-            _ => Card::Normal(Pattern::Spade, 14),
-        }
+    fn get_mighty(&self, rule: &Rule) -> Option<Card> {
+        rule.mighty.get(self.get_giruda())
     }
 
     #[cfg(feature = "server")]
@@ -176,10 +294,11 @@ impl State {
     }
 
     #[cfg(feature = "server")]
-    fn check_card_effect(&self, c: (CardPolicy, CardPolicy)) -> bool {
+    fn check_card_effect(&self, rule: &Rule, c: (CardPolicy, CardPolicy)) -> bool {
         match self {
             State::InGame { turn_count, .. } => {
-                (*turn_count == 0 && c.0 == CardPolicy::NoEffect) || (*turn_count == 9 && c.1 == CardPolicy::NoEffect)
+                (*turn_count == 0 && c.0 == CardPolicy::NoEffect)
+                    || (*turn_count == rule.last_turn() && c.1 == CardPolicy::NoEffect)
             }
             _ => unreachable!(),
         }
@@ -234,9 +353,10 @@ impl State {
     /// Calculating winner of cards
     #[cfg(feature = "server")]
     pub fn calculate_winner(&self, rule: &Rule, cards: &[Card]) -> Card {
-        let mighty = self.get_mighty();
-        if cards.contains(&mighty) {
-            return mighty;
+        if let Some(mighty) = self.get_mighty(rule) {
+            if cards.contains(&mighty) {
+                return mighty;
+            }
         }
 
         if rule.deck.1 == 0b01 || rule.deck.1 == 0b10 {
@@ -286,20 +406,38 @@ impl State {
 impl State {
     #[cfg(feature = "server")]
     pub fn new(rule: &Rule) -> State {
-        let mut deck = State::get_random_deck(rule);
-        let left = deck.pop().unwrap();
-        State::Election {
-            pledge: vec![None; 5],
-            done: vec![false; 5],
-            curr_user: 0,
-            start_user: None,
-            deck,
-            left,
-        }
+        State::deal(rule, 0, None, &mut rand::thread_rng())
+    }
+
+    /// Deals the cards from the seed, so that the same seed gives the same hands.
+    /// Only the first deal is seeded, and the deals after the missed deal are random.
+    #[cfg(feature = "server")]
+    pub fn from_seed(rule: &Rule, seed: u64) -> State {
+        State::from_rng(rule, &mut StdRng::seed_from_u64(seed))
+    }
+
+    /// Deals the cards with `rng`, which can be given to `State::next_with_rng` for the rest of the game.
+    #[cfg(feature = "server")]
+    pub fn from_rng<R: Rng>(rule: &Rule, rng: &mut R) -> State {
+        State::deal(rule, 0, None, rng)
     }
 
     #[cfg(feature = "server")]
     pub fn next(&self, user_id: usize, cmd: Command, rule: &Rule) -> Result<Self> {
+        self.next_with_rng(user_id, cmd, rule, &mut rand::thread_rng())
+    }
+
+    /// `State::next` which makes every random choice with `rng`,
+    /// such as `Command::Random`, the president among the tie, and the deal after the missed deal.
+    /// The same commands with the same seeded `rng` give the same state.
+    #[cfg(feature = "server")]
+    pub fn next_with_rng<R: Rng>(&self, user_id: usize, cmd: Command, rule: &Rule, rng: &mut R) -> Result<Self> {
+        // random command is chosen by the user, and played by the seat
+        let user_id = if matches!(cmd, Command::Random) {
+            user_id
+        } else {
+            self.seat(user_id, rule)
+        };
         match self {
             State::Election {
                 pledge,
@@ -308,25 +446,26 @@ impl State {
                 start_user,
                 deck,
                 left,
+                revealed,
             } => match cmd {
                 Command::Pledge(x) => {
                     let mut done = done.clone();
                     let mut pledge = pledge.clone();
                     let is_ordered = rule.election.contains(election::Election::ORDERED);
                     if *curr_user != user_id && is_ordered {
-                        return Err(Error::InvalidOrder);
+                        return Err(Error::NotYourTurn(user_id));
                     }
 
                     match x {
                         Some((c, p)) => {
                             if p > rule.pledge.max {
-                                return Err(Error::InvalidPledge(true, rule.pledge.max));
+                                return Err(Error::PledgeTooHigh(rule.pledge.max));
                             }
                             if c == None && !rule.election.contains(election::Election::NO_GIRUDA_EXIST) {
-                                return Err(Error::InvalidPledge(true, 0));
+                                return Err(Error::NoGiruda);
                             }
                             if done[user_id] {
-                                return Err(Error::InvalidPledge(true, 0));
+                                return Err(Error::AlreadyPassed(user_id));
                             }
                             let start_user = if *start_user == None {
                                 user_id
@@ -349,10 +488,10 @@ impl State {
                                 (max_pledge as i8 + offset) as u8
                             };
                             if p < std::cmp::max(max_pledge, rule.pledge.min) {
-                                return Err(Error::InvalidPledge(false, max_pledge));
+                                return Err(Error::PledgeTooLow(std::cmp::max(max_pledge, rule.pledge.min)));
                             }
                             if p == max_pledge && rule.election.contains(election::Election::INCREASING) {
-                                return Err(Error::InvalidPledge(false, max_pledge));
+                                return Err(Error::PledgeNotIncreasing(max_pledge));
                             }
 
                             pledge[user_id] = Some((c, p));
@@ -364,6 +503,7 @@ impl State {
                                 start_user: Some(start_user),
                                 deck: deck.clone(),
                                 left: left.clone(),
+                                revealed: revealed.clone(),
                             })
                         }
                         _ => {
@@ -409,7 +549,7 @@ impl State {
                             if is_election_done {
                                 let mut deck = deck.clone();
                                 let mut left = left.clone();
-                                let president = candidate.choose(&mut rand::thread_rng()).copied().unwrap();
+                                let president = candidate.choose(rng).copied().unwrap();
                                 let mut pledge = pledge[president];
                                 if last_max == 0 {
                                     let mut pledge_vec = vec![
@@ -422,14 +562,16 @@ impl State {
                                         pledge_vec
                                             .push((None, (rule.pledge.min as i8 + rule.pledge.no_giruda_offset) as u8));
                                     }
-                                    pledge = Some(pledge_vec.choose(&mut rand::thread_rng()).copied().unwrap());
+                                    pledge = Some(pledge_vec.choose(rng).copied().unwrap());
                                 }
+                                let kitty = left.clone();
                                 deck[president].append(&mut left);
                                 Ok(State::SelectFriend {
                                     president,
                                     giruda: pledge.unwrap().0,
                                     pledge: pledge.unwrap().1,
                                     deck,
+                                    kitty,
                                 })
                             } else {
                                 Ok(State::Election {
@@ -439,55 +581,118 @@ impl State {
                                     start_user: *start_user,
                                     deck: deck.clone(),
                                     left: left.clone(),
+                                    revealed: revealed.clone(),
                                 })
                             }
                         }
                     }
                 }
-                Command::Random => self.next(user_id, Command::Pledge(None), rule),
-                _ => Err(Error::InvalidCommand("Command::Pledge")),
+                Command::DeclareMissedDeal => {
+                    if rule.missed_deal.declare != Declare::Voluntary {
+                        return Err(Error::DeclareNotAllowed);
+                    }
+                    if start_user.is_some() || done.iter().any(|&d| d) {
+                        return Err(Error::ElectionStarted);
+                    }
+                    if !rule.missed_deal.is_missed_deal(&deck[user_id]) {
+                        return Err(Error::NotMissedDeal);
+                    }
+                    Ok(State::deal(
+                        rule,
+                        rule.missed_deal.redeal.dealer(*curr_user, user_id),
+                        Some((user_id, deck[user_id].clone())),
+                        rng,
+                    ))
+                }
+                Command::Random => self.next_random(user_id, rule, rng),
+                _ => Err(Error::InvalidCommand(self.phase())),
             },
             State::SelectFriend {
                 president,
                 giruda,
                 pledge,
                 deck,
+                kitty,
             } => match cmd {
                 Command::SelectFriend(drop_card, friend_func) => {
+                    if user_id != *president {
+                        return Err(Error::NotYourTurn(user_id));
+                    }
                     let mut deck = deck.clone();
-                    if drop_card.len() != deck[user_id].len() - deck[(user_id + 1) % (rule.user_cnt as usize)].len() {
-                        return Err(Error::DropCard);
+                    let drop_cnt = rule.kitty_size();
+                    if drop_card.len() != drop_cnt {
+                        return Err(Error::DropCard(drop_cnt, drop_card.len()));
                     }
                     for card in drop_card.iter() {
-                        let idx = deck[user_id].iter().position(|x| *x == *card).ok_or(Error::NotInDeck)?;
+                        let idx = deck[user_id]
+                            .iter()
+                            .position(|x| *x == *card)
+                            .ok_or(Error::NotInDeck(*card))?;
                         deck[user_id].remove(idx);
                     }
+                    if let Some(max) = rule.kitty.max_exchange {
+                        if State::remove_cards(kitty, &drop_card).len() > max as usize {
+                            return Err(Error::KittyExchange(max));
+                        }
+                    }
                     let friend = match friend_func {
                         FriendFunc::ByCard(c) => {
                             if !rule.friend.contains(friend::Friend::CARD) {
-                                return Err(Error::InvalidFriendFunc);
+                                return Err(Error::FriendNotAllowed(friend_func));
                             }
-                            let temp = deck
+                            // cards of the dummy hands are the president's
+                            let mut holders = deck
                                 .iter()
                                 .enumerate()
                                 .filter(|(_, d)| d.contains(&c))
-                                .map(|(i, _)| i)
-                                .next();
-                            if temp.unwrap() == *president && !rule.friend.contains(friend::Friend::FAKE) {
-                                return Err(Error::InvalidFriendFunc);
+                                .map(|(i, _)| if rule.is_dummy(i) { *president } else { i })
+                                .collect::<Vec<_>>();
+                            holders.sort_unstable();
+                            holders.dedup();
+                            if holders == [*president] && !rule.friend.contains(friend::Friend::FAKE) {
+                                return Err(Error::FakeFriend(c));
+                            }
+                            // with duplicate cards, the friend is decided when one of them places the card
+                            let others = holders.iter().copied().filter(|u| u != president).collect::<Vec<_>>();
+                            match others.len() {
+                                0 => holders.first().copied(),
+                                1 => Some(others[0]),
+                                _ => None,
+                            }
+                        }
+                        FriendFunc::ByUser(u) => {
+                            if !rule.friend.contains(friend::Friend::PICK) {
+                                return Err(Error::FriendNotAllowed(friend_func));
                             }
-                            temp
+                            if u >= rule.user_cnt as usize || u == *president {
+                                return Err(Error::InvalidFriend(u));
+                            }
+                            Some(u)
                         }
-                        FriendFunc::ByUser(u) => Some(u).filter(|_| u != *president),
                         FriendFunc::None => {
                             if !rule.friend.contains(friend::Friend::NONE) {
-                                return Err(Error::InvalidFriendFunc);
+                                return Err(Error::FriendNotAllowed(friend_func));
+                            }
+                            None
+                        }
+                        FriendFunc::First => {
+                            if !rule.friend.contains(friend::Friend::FIRST) {
+                                return Err(Error::FriendNotAllowed(friend_func));
+                            }
+                            None
+                        }
+                        FriendFunc::Last => {
+                            if !rule.friend.contains(friend::Friend::LAST) {
+                                return Err(Error::FriendNotAllowed(friend_func));
                             }
                             None
                         }
-                        _ => None,
                     };
-                    let is_friend_known = matches!(&friend_func, FriendFunc::None | FriendFunc::ByUser(_));
+                    let is_friend_known = match friend_func {
+                        FriendFunc::None => true,
+                        FriendFunc::ByUser(_) => rule.friend_reveal == FriendReveal::OnPlay,
+                        _ => false,
+                    };
                     Ok(State::InGame {
                         president: *president,
                         friend_func,
@@ -496,12 +701,11 @@ impl State {
                         giruda: *giruda,
                         pledge: *pledge,
                         deck,
-                        score_deck: vec![Vec::new(); rule.user_cnt as usize],
+                        dropped: drop_card,
+                        score_deck: vec![Vec::new(); rule.seat_cnt()],
+                        tricks: Vec::new(),
                         turn_count: 0,
-                        placed_cards: vec![
-                            (Card::Normal(Pattern::Spade, 0), CardPolicy::Valid);
-                            rule.user_cnt as usize
-                        ],
+                        placed_cards: vec![(Card::Normal(Pattern::Spade, 0), CardPolicy::Valid); rule.seat_cnt()],
                         start_user: *president,
                         current_user: *president,
                         current_pattern: Rush::from(Pattern::Spade),
@@ -510,8 +714,11 @@ impl State {
                     })
                 }
                 Command::ChangePledge(new_giruda) => {
+                    if user_id != *president {
+                        return Err(Error::NotYourTurn(user_id));
+                    }
                     if *giruda == new_giruda {
-                        return Err(Error::SameGiruda);
+                        return Err(Error::SameGiruda(new_giruda));
                     }
 
                     let new_pledge = if matches!(giruda, None) {
@@ -523,7 +730,7 @@ impl State {
                     };
 
                     if new_pledge > rule.pledge.max {
-                        return Err(Error::InvalidPledge(true, rule.pledge.max));
+                        return Err(Error::PledgeTooHigh(rule.pledge.max));
                     }
 
                     Ok(State::SelectFriend {
@@ -531,23 +738,11 @@ impl State {
                         giruda: new_giruda,
                         pledge: new_pledge,
                         deck: deck.clone(),
+                        kitty: kitty.clone(),
                     })
                 }
-                Command::Random => self.next(
-                    user_id,
-                    Command::SelectFriend(
-                        deck[user_id]
-                            .choose_multiple(
-                                &mut rand::thread_rng(),
-                                deck[user_id].len() - deck[(user_id + 1) % (rule.user_cnt as usize)].len(),
-                            )
-                            .cloned()
-                            .collect(),
-                        FriendFunc::None,
-                    ),
-                    rule,
-                ),
-                _ => Err(Error::InvalidCommand("Command::Pledge")),
+                Command::Random => self.next_random(user_id, rule, rng),
+                _ => Err(Error::InvalidCommand(self.phase())),
             },
             State::InGame {
                 president,
@@ -557,7 +752,9 @@ impl State {
                 giruda,
                 pledge,
                 deck,
+                dropped,
                 score_deck,
+                tricks,
                 turn_count,
                 placed_cards,
                 start_user,
@@ -571,6 +768,7 @@ impl State {
                     let mut is_friend_known = *is_friend_known;
                     let mut deck = deck.clone();
                     let mut score_deck = score_deck.clone();
+                    let mut tricks = tricks.clone();
                     let mut turn_count = *turn_count;
                     let mut placed_cards = placed_cards.clone();
                     let mut start_user = *start_user;
@@ -580,43 +778,44 @@ impl State {
 
                     placed_cards[user_id] = (card, CardPolicy::Valid);
 
-                    is_friend_known = match friend_func {
-                        FriendFunc::ByCard(c) => *c == card,
-                        _ => is_friend_known,
-                    };
+                    let is_friend_card = matches!(friend_func, FriendFunc::ByCard(c) if *c == card);
+                    if is_friend_card && friend.is_none() && user_id != *president && !rule.is_dummy(user_id) {
+                        friend = Some(user_id);
+                    }
+                    if rule.friend_reveal == FriendReveal::OnPlay {
+                        is_friend_known |= is_friend_card && friend == Some(user_id);
+                    }
 
-                    let mut joker_calls = Vec::new();
+                    let mighty = self.get_mighty(rule);
+                    let joker_calls = State::joker_calls(rule, *giruda);
                     let mut is_noeffect = false;
-                    for cards in &rule.joker_call.cards {
-                        joker_calls.push(if matches!(*giruda, Some(y) if Rush::from(y) == Rush::from(cards.0)) {
-                            cards.1
-                        } else {
-                            cards.0
-                        });
-                    }
-                    if joker_call_card != None {
-                        if !deck[user_id]
-                            .iter()
-                            .all(|x| matches!(joker_call_card, Some(y) if y == *x) || card == *x)
-                        {
-                            return Err(Error::JokerCall);
-                        } else if joker_call_effect {
+                    // the mighty can be placed instead of the called joker
+                    let is_mighty_defense = rule.joker_call.mighty_defense && Some(card) == mighty;
+                    // the user who has the called joker should place it
+                    if let Some(called) = joker_call_card.filter(|_| !is_mighty_defense) {
+                        if !card.is_joker() && deck[user_id].iter().any(Card::is_joker) {
+                            return Err(Error::JokerCall(called));
+                        } else if card.is_joker() && joker_call_effect {
                             is_noeffect = true;
                         }
                     }
 
-                    let idx = deck[user_id].iter().position(|x| *x == card).ok_or(Error::NotInDeck)?;
-                    if turn_count == 0 || turn_count == 9 {
-                        if card == self.get_mighty() {
+                    let idx = deck[user_id]
+                        .iter()
+                        .position(|x| *x == card)
+                        .ok_or(Error::NotInDeck(card))?;
+                    if turn_count == 0 || turn_count == rule.last_turn() {
+                        if Some(card) == mighty {
                             if self.check_card_valid(rule.card_policy.mighty) {
-                                return Err(Error::WrongCard);
+                                return Err(Error::RestrictedCard(card, turn_count));
                             }
-                            if self.check_card_effect(rule.card_policy.mighty) {
+                            if self.check_card_effect(rule, rule.card_policy.mighty) {
                                 is_noeffect = true;
                             }
                         } else if matches!(rule.card_policy.card.get(&card), Some(y) if self.check_card_valid(*y)) {
-                            return Err(Error::WrongCard);
-                        } else if matches!(rule.card_policy.card.get(&card), Some(y) if self.check_card_effect(*y)) {
+                            return Err(Error::RestrictedCard(card, turn_count));
+                        } else if matches!(rule.card_policy.card.get(&card), Some(y) if self.check_card_effect(rule, *y))
+                        {
                             is_noeffect = true;
                         } else {
                             match card {
@@ -627,9 +826,9 @@ impl State {
                                             .all(|x| matches!(*giruda, Some(y) if Rush::from(y) == Rush::from(*x)))
                                     {
                                         if self.check_card_valid(rule.card_policy.giruda) {
-                                            return Err(Error::WrongCard);
+                                            return Err(Error::RestrictedCard(card, turn_count));
                                         }
-                                        if self.check_card_effect(rule.card_policy.giruda) {
+                                        if self.check_card_effect(rule, rule.card_policy.giruda) {
                                             is_noeffect = true;
                                         }
                                     }
@@ -637,14 +836,14 @@ impl State {
                                         && user_joker_call
                                         && self.check_card_valid(rule.card_policy.joker_call)
                                     {
-                                        return Err(Error::WrongCard);
+                                        return Err(Error::RestrictedCard(card, turn_count));
                                     }
                                 }
                                 Card::Joker(_) => {
                                     if self.check_card_valid(rule.card_policy.joker) {
-                                        return Err(Error::WrongCard);
+                                        return Err(Error::RestrictedCard(card, turn_count));
                                     }
-                                    if self.check_card_effect(rule.card_policy.joker) {
+                                    if self.check_card_effect(rule, rule.card_policy.joker) {
                                         is_noeffect = true;
                                     }
                                 }
@@ -661,12 +860,12 @@ impl State {
                         joker_call_effect = false;
 
                         if !deck[user_id].iter().all(|x| match *x {
-                            Card::Normal(t, _) => *x == self.get_mighty() || matches!(giruda, Some(y) if t == *y),
+                            Card::Normal(t, _) => Some(*x) == mighty || matches!(giruda, Some(y) if t == *y),
                             Card::Joker(_) => true,
                         }) && matches!(giruda, Some(y) if Rush::from(*y) == current_pattern)
                             && rule.card_policy.giruda.0 == CardPolicy::InvalidForFirst
                         {
-                            return Err(Error::WrongCard);
+                            return Err(Error::GirudaLead(giruda.unwrap()));
                         }
 
                         match card {
@@ -674,7 +873,8 @@ impl State {
                                 if joker_calls.contains(&card) && user_joker_call {
                                     joker_call_card = Some(card);
                                     if !(rule.card_policy.joker_call.0 == CardPolicy::NoEffect && turn_count == 0
-                                        || rule.card_policy.joker_call.1 == CardPolicy::NoEffect && turn_count == 9)
+                                        || rule.card_policy.joker_call.1 == CardPolicy::NoEffect
+                                            && turn_count == rule.last_turn())
                                     {
                                         joker_call_effect = true;
                                     }
@@ -688,63 +888,66 @@ impl State {
                                     Color::Red => Rush::red().contains(current_pattern),
                                 };
                                 if !containing {
-                                    return Err(Error::WrongPattern);
+                                    return Err(Error::WrongPattern(card, current_pattern));
                                 }
                             }
                         }
                         deck[user_id].remove(idx);
-                    } else if self.get_mighty() == card {
+                    } else if mighty == Some(card) {
                         deck[user_id].remove(idx);
                     } else if !deck[user_id].iter().all(|x| !current_pattern == Rush::from(*x))
                         && !current_pattern == Rush::from(card)
                     {
-                        return Err(Error::WrongCard);
+                        return Err(Error::MustFollow(current_pattern));
                     } else {
                         deck[user_id].remove(idx);
                     }
 
-                    let mut next_user = (*current_user + 1) % 5;
+                    let mut next_user = (*current_user + 1) % rule.seat_cnt();
 
                     if next_user == start_user {
-                        let mut card_vec = Vec::new();
-
-                        for i in 0..(rule.user_cnt as usize) {
-                            let (c, p) = placed_cards[(i + start_user) % (rule.user_cnt as usize)];
-
-                            if p == CardPolicy::NoEffect {
-                                continue;
-                            }
-                            card_vec.push(c);
+                        let order = (0..rule.seat_cnt()).map(|i| (i + start_user) % rule.seat_cnt());
+                        let mut users = order
+                            .clone()
+                            .filter(|u| placed_cards[*u].1 != CardPolicy::NoEffect)
+                            .collect::<Vec<_>>();
+                        if users.is_empty() {
+                            users = order.collect();
                         }
-                        if card_vec.is_empty() {
-                            for i in 0..(rule.user_cnt as usize) {
-                                let (c, _) = placed_cards[(i + start_user) % (rule.user_cnt as usize)];
-                                card_vec.push(c);
-                            }
+                        let card_vec = users.iter().map(|u| placed_cards[*u].0).collect::<Vec<_>>();
+                        let winner_card = self.calculate_winner(rule, &card_vec);
+                        // identical cards in the deck with duplicates are decided by the placed order
+                        let mut same = users.into_iter().filter(|u| placed_cards[*u].0 == winner_card);
+                        let winner = match rule.tie_break {
+                            TieBreak::First => same.next(),
+                            TieBreak::Last => same.next_back(),
                         }
-                        let winner_card = self.calculate_winner(&rule, &card_vec);
-                        let winner = placed_cards[start_user..]
-                            .iter()
-                            .position(|(c, _)| *c == winner_card)
-                            .unwrap_or_else(|| {
-                                placed_cards[..start_user]
-                                    .iter()
-                                    .position(|(c, _)| *c == winner_card)
-                                    .unwrap()
-                            });
+                        .ok_or(Error::Internal("no winner of the trick"))?;
 
+                        // the president can't be the friend by taking the trick with the dummy hand
+                        let can_be_friend = winner != *president && !rule.is_dummy(winner);
                         if let FriendFunc::First = friend_func {
-                            friend =
-                                friend.or_else(|| Some(winner).filter(|_| turn_count == 0 && winner != *president));
-                            is_friend_known |= turn_count == 0;
+                            friend = friend.or_else(|| Some(winner).filter(|_| turn_count == 0 && can_be_friend));
                         }
 
                         if let FriendFunc::Last = friend_func {
-                            friend =
-                                friend.or_else(|| Some(winner).filter(|_| turn_count == 9 && winner != *president));
-                            is_friend_known |= turn_count == 9;
+                            friend = friend
+                                .or_else(|| Some(winner).filter(|_| turn_count == rule.last_turn() && can_be_friend));
                         }
 
+                        is_friend_known |= match rule.friend_reveal {
+                            FriendReveal::OnPlay => match friend_func {
+                                FriendFunc::First => turn_count == 0,
+                                FriendFunc::Last => turn_count == rule.last_turn(),
+                                _ => false,
+                            },
+                            FriendReveal::FirstTrick => match friend_func {
+                                FriendFunc::Last => turn_count == rule.last_turn(),
+                                _ => true,
+                            },
+                            FriendReveal::End => false,
+                        };
+
                         {
                             let mut score_cards = placed_cards
                                 .iter()
@@ -753,51 +956,75 @@ impl State {
                             score_deck[winner].append(&mut score_cards);
                         }
 
+                        tricks.push(Trick {
+                            leader: start_user,
+                            cards: (0..rule.seat_cnt())
+                                .map(|i| placed_cards[(i + start_user) % rule.seat_cnt()])
+                                .collect(),
+                            rush: current_pattern,
+                            joker_call: joker_call_card.is_some(),
+                            winner,
+                        });
+                        // joker call lasts only for the trick
+                        joker_call_card = None;
+                        joker_call_effect = false;
+
                         start_user = winner;
                         next_user = start_user;
                         turn_count += 1;
 
-                        if turn_count == 10 {
-                            let mut mul = 1;
-                            if matches!(giruda, None) {
-                                mul *= 2;
-                            }
-                            if matches!(friend_func, FriendFunc::None) {
-                                mul *= 2;
-                            }
-
+                        if turn_count == rule.card_cnt_per_user {
                             let president = *president;
                             let pledge = *pledge;
 
-                            let mut score = score_deck.iter().map(|x| x.len() as u8).sum();
-                            let mut winner = 1 << president;
-                            if let Some(f) = friend {
-                                score -= score_deck[f].len() as u8;
-                                winner += 1 << f;
-                            }
-                            score = 20 - score + score_deck[president].len() as u8;
-                            if score == 20 {
-                                mul *= 2;
-                            }
-
-                            if score >= pledge {
-                                score = mul * (score - 10);
-                            } else {
-                                score = if score <= 10 {
-                                    2 * (pledge - score)
-                                } else {
-                                    pledge - score
-                                };
-                                winner = (1 << 5) - winner;
+                            // dummy hands are played by the president, so they take points for the ruling party
+                            let mut ruling = 1 << president | rule.dummy_seats();
+                            // fake friend is the president itself
+                            if let Some(f) = friend.filter(|f| *f != president) {
+                                ruling |= 1 << f;
                             }
+                            let captured = |is_ruling: bool| {
+                                score_deck
+                                    .iter()
+                                    .enumerate()
+                                    .filter(|(i, _)| (ruling & 1 << i != 0) == is_ruling)
+                                    .map(|(_, d)| d.len() as u8)
+                                    .sum::<u8>()
+                            };
+                            let dropped_score = dropped.iter().filter(|c| c.is_score()).count() as u8;
+                            let (points, opposition_points) = match rule.kitty.points {
+                                KittyPoints::Ruling => (captured(true) + dropped_score, captured(false)),
+                                KittyPoints::Opposition => (captured(true), captured(false) + dropped_score),
+                                KittyPoints::Nobody => (captured(true), captured(false)),
+                            };
+                            let (is_win, score, multipliers) = rule.scoring.game_score(
+                                pledge,
+                                points,
+                                opposition_points == 0,
+                                giruda.is_none(),
+                                matches!(friend_func, FriendFunc::None),
+                            );
+                            let users = (1 << rule.user_cnt) - 1;
+                            let winner = if is_win { ruling & users } else { !ruling & users };
+                            let scores = (0..rule.user_cnt as usize)
+                                .map(|i| {
+                                    let sign = if winner & 1 << i != 0 { 1 } else { -1 };
+                                    sign * score * rule.scoring.share_of(i, president, friend)
+                                })
+                                .collect();
 
                             return Ok(State::GameEnded {
                                 winner,
                                 president,
                                 friend,
-                                score,
+                                scores,
                                 pledge,
                                 giruda: *giruda,
+                                score_deck,
+                                dropped: dropped.clone(),
+                                points,
+                                multipliers,
+                                tricks,
                             });
                         }
                     }
@@ -810,7 +1037,9 @@ impl State {
                         giruda: *giruda,
                         pledge: *pledge,
                         deck,
+                        dropped: dropped.clone(),
                         score_deck,
+                        tricks,
                         turn_count,
                         placed_cards,
                         start_user,
@@ -820,16 +1049,182 @@ impl State {
                         joker_call_effect,
                     })
                 }
-                Command::Random => {
-                    let rand_card = deck[user_id].choose(&mut rand::thread_rng()).unwrap();
-                    self.next(user_id, Command::Go(*rand_card, Rush::from(*rand_card), false), rule)
-                }
-                _ => Err(Error::InvalidCommand("BasicCommand::Go")),
+                Command::Random => self.next_random(user_id, rule, rng),
+                _ => Err(Error::InvalidCommand(self.phase())),
             },
             _ => Ok(self.clone()),
         }
     }
 
+    /// Commands which the user can send in this state, every one of them is accepted by `next`.
+    ///
+    /// - `Election`: every valid pledge, passing and declaring missed deal
+    /// - `SelectFriend`: every valid giruda change and friend function.
+    ///   The kitty is dropped back in every `SelectFriend`, since it is valid under any `Kitty::max_exchange`,
+    ///   and the other cards which can be dropped are given by `drop_options`.
+    /// - `InGame`: every card which can be placed, with each pattern the leading joker can take
+    ///   and with or without joker call for the leading joker call card
+    ///
+    /// Empty if the user can't do anything now.
+    #[cfg(feature = "server")]
+    pub fn legal_commands(&self, user_id: usize, rule: &Rule) -> Vec<Command> {
+        let drop_card = match self {
+            State::SelectFriend { president, kitty, .. } if *president == user_id => kitty.clone(),
+            _ => Vec::new(),
+        };
+        self.candidate_commands(user_id, rule, drop_card)
+            .into_iter()
+            .filter(|cmd| self.next(user_id, cmd.clone(), rule).is_ok())
+            .collect()
+    }
+
+    /// Next state with the command chosen uniformly from legal commands.
+    /// Dropped cards in `SelectFriend` are also chosen randomly.
+    #[cfg(feature = "server")]
+    fn next_random<R: Rng>(&self, user_id: usize, rule: &Rule, rng: &mut R) -> Result<Self> {
+        let drop_card = match self {
+            State::SelectFriend {
+                president, deck, kitty, ..
+            } if *president == user_id => match rule.kitty.max_exchange {
+                None => deck[user_id].choose_multiple(rng, rule.kitty_size()).cloned().collect(),
+                Some(max) => {
+                    // keep some of the kitty, and drop the same count from the rest
+                    let keep = rng.gen_range(0..=kitty.len().min(max as usize));
+                    let rest = State::remove_cards(&deck[user_id], kitty);
+                    kitty
+                        .choose_multiple(rng, kitty.len() - keep)
+                        .chain(rest.choose_multiple(rng, keep))
+                        .cloned()
+                        .collect()
+                }
+            },
+            _ => Vec::new(),
+        };
+        let cmd = self
+            .candidate_commands(user_id, rule, drop_card)
+            .into_iter()
+            .filter(|cmd| self.next(user_id, cmd.clone(), rule).is_ok())
+            .choose(rng)
+            .ok_or(Error::NotYourTurn(user_id))?;
+        self.next_with_rng(user_id, cmd, rule, rng)
+    }
+
+    /// Seat which the user plays, the president plays the dummy hand in its turn
+    #[cfg(feature = "server")]
+    fn seat(&self, user_id: usize, rule: &Rule) -> usize {
+        match self {
+            State::InGame {
+                president,
+                current_user,
+                ..
+            } if user_id == *president && rule.is_dummy(*current_user) => *current_user,
+            _ => user_id,
+        }
+    }
+
+    /// `cards` without `removed`, counting duplicates
+    #[cfg(feature = "server")]
+    fn remove_cards(cards: &[Card], removed: &[Card]) -> Vec<Card> {
+        let mut removed = removed.to_vec();
+        cards
+            .iter()
+            .filter(|c| match removed.iter().position(|x| x == *c) {
+                Some(i) => {
+                    removed.swap_remove(i);
+                    false
+                }
+                None => true,
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Count of cards which the president should drop, and the cards which can be dropped.
+    /// Any combination of the count can be dropped,
+    /// while at most `Kitty::max_exchange` of them can be the cards which are not in the kitty.
+    ///
+    /// `None` if the user doesn't select the friend now.
+    #[cfg(feature = "server")]
+    pub fn drop_options(&self, user_id: usize, rule: &Rule) -> Option<(usize, Vec<Card>)> {
+        match self {
+            State::SelectFriend { president, deck, .. } if *president == user_id => {
+                Some((rule.kitty_size(), deck[user_id].clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Commands to try for `legal_commands`, some of them can be rejected by `next`
+    #[cfg(feature = "server")]
+    pub(crate) fn candidate_commands(&self, user_id: usize, rule: &Rule, drop_card: Vec<Card>) -> Vec<Command> {
+        if self.valid_users(rule) & (1 << user_id) == 0 {
+            return Vec::new();
+        }
+        let girudas = [
+            Some(Pattern::Spade),
+            Some(Pattern::Diamond),
+            Some(Pattern::Heart),
+            Some(Pattern::Clover),
+            None,
+        ];
+        match self {
+            State::Election { .. } => {
+                let mut commands = vec![Command::Pledge(None), Command::DeclareMissedDeal];
+                for giruda in girudas.iter() {
+                    for pledge in 0..=rule.pledge.max {
+                        commands.push(Command::Pledge(Some((*giruda, pledge))));
+                    }
+                }
+                commands
+            }
+            State::SelectFriend { .. } => {
+                let mut cards = rule.deck.0.clone();
+                cards.sort();
+                cards.dedup();
+                let friend_funcs = vec![FriendFunc::None, FriendFunc::First, FriendFunc::Last]
+                    .into_iter()
+                    .chain((0..rule.seat_cnt()).map(FriendFunc::ByUser))
+                    .chain(cards.into_iter().map(FriendFunc::ByCard));
+                girudas
+                    .iter()
+                    .map(|g| Command::ChangePledge(*g))
+                    .chain(friend_funcs.map(|f| Command::SelectFriend(drop_card.clone(), f)))
+                    .collect()
+            }
+            State::InGame {
+                giruda,
+                deck,
+                start_user,
+                current_user,
+                ..
+            } => {
+                let is_leading = start_user == current_user;
+                let joker_calls = State::joker_calls(rule, *giruda);
+                let mut cards = deck[self.seat(user_id, rule)].clone();
+                cards.sort();
+                cards.dedup();
+                let mut commands = Vec::new();
+                for card in cards {
+                    match card {
+                        Card::Joker(_) if is_leading => {
+                            for p in girudas.iter().flatten() {
+                                commands.push(Command::Go(card, Rush::from(*p), false));
+                            }
+                        }
+                        _ => {
+                            commands.push(Command::Go(card, Rush::from(card), false));
+                            if is_leading && joker_calls.contains(&card) {
+                                commands.push(Command::Go(card, Rush::from(card), true));
+                            }
+                        }
+                    }
+                }
+                commands
+            }
+            State::GameEnded { .. } => Vec::new(),
+        }
+    }
+
     /*
     #[cfg(feature = "client")]
     pub fn is_valid_command(&self, user_id: usize, cmd: Command, rule: &Rule) -> Result<()> {
@@ -906,7 +1301,7 @@ impl State {
                 Command::SelectFriend(drop_card, friend_func) => {
                     let mut deck = deck.clone();
                     for card in drop_card.iter() {
-                        let idx = deck[user_id].iter().position(|x| *x == *card).ok_or(Error::NotInDeck)?;
+                        let idx = deck[user_id].iter().position(|x| *x == *card).ok_or(Error::NotInDeck(*card))?;
                         deck[user_id].remove(idx);
                     }
                     match friend_func {
@@ -934,6 +1329,9 @@ impl State {
                     Ok(())
                 }
                 Command::ChangePledge(new_giruda) => {
+                    if user_id != *president {
+                        return Err(Error::NotYourTurn(user_id));
+                    }
                     if *giruda == new_giruda {
                         return Err(Error::SameGiruda);
                     }
@@ -999,7 +1397,7 @@ impl State {
                         return Err(Error::JokerCall);
                     }
 
-                    deck[user_id].iter().position(|x| *x == card).ok_or(Error::NotInDeck)?;
+                    deck[user_id].iter().position(|x| *x == card).ok_or(Error::NotInDeck(card))?;
                     if turn_count == 0 || turn_count == 9 {
                         if card == self.get_mighty() && self.check_card_valid(rule.card_policy.mighty)
                             || matches!(rule.card_policy.card.get(&card), Some(y) if self.check_card_valid(*y))
@@ -1064,28 +1462,84 @@ impl State {
     }*/
 
     /// Valid users to action next time.
-    /// Result is 8-bit integer which contains 0 or 1 for each user.
+    /// Result is 16-bit integer which contains 0 or 1 for each user.
     /// If all users all valid to action, the result would be `(1 << N) - 1`
-    pub fn valid_users(&self, rule: &Rule) -> u8 {
+    pub fn valid_users(&self, rule: &Rule) -> u16 {
         match self {
-            State::Election { curr_user, .. } => {
-                if rule.election.contains(election::Election::ORDERED) {
-                    1 << *curr_user
-                } else {
-                    (1 << rule.user_cnt) - 1
+            State::Election {
+                curr_user,
+                start_user,
+                done,
+                deck,
+                ..
+            } => {
+                if !rule.election.contains(election::Election::ORDERED) {
+                    return (1 << rule.user_cnt) - 1;
                 }
+                // users with missed deal can declare it before the election starts
+                let can_declare =
+                    rule.missed_deal.declare == Declare::Voluntary && start_user.is_none() && done.iter().all(|&d| !d);
+                deck.iter()
+                    .take(rule.user_cnt as usize)
+                    .enumerate()
+                    .filter(|(_, d)| can_declare && rule.missed_deal.is_missed_deal(d))
+                    .fold(1 << *curr_user, |users, (i, _)| users | 1 << i)
             }
             State::SelectFriend { president, .. } => 1 << *president,
-            State::InGame { current_user, .. } => 1 << *current_user,
+            State::InGame {
+                president,
+                current_user,
+                ..
+            } => {
+                if rule.is_dummy(*current_user) {
+                    1 << *president
+                } else {
+                    1 << *current_user
+                }
+            }
             _ => 0,
         }
     }
+
+    /// State without cards in hands (and left cards in election).
+    /// This is for who shouldn't know the hands, like observers.
+    /// Placed cards and score cards are still visible, and the kitty is visible if `KittyReveal::All`.
+    /// Dummy hands are always visible, and every hand is visible if `Table::open_hand`.
+    pub fn hide_hands(&self, rule: &Rule) -> State {
+        let hidden = if rule.table.open_hand {
+            0
+        } else {
+            rule.user_cnt as usize
+        };
+        let mut state = self.clone();
+        match &mut state {
+            State::Election { deck, left, .. } => {
+                deck.iter_mut().take(hidden).for_each(Vec::clear);
+                left.clear();
+            }
+            State::SelectFriend { deck, kitty, .. } => {
+                deck.iter_mut().take(hidden).for_each(Vec::clear);
+                if rule.kitty.reveal != KittyReveal::All {
+                    kitty.clear();
+                }
+            }
+            State::InGame { deck, dropped, .. } => {
+                deck.iter_mut().take(hidden).for_each(Vec::clear);
+                dropped.clear();
+            }
+            State::GameEnded { .. } => {}
+        }
+        state
+    }
 }
 
 #[cfg(test)]
 mod test {
     #[cfg(feature = "server")]
-    use {super::*, crate::prelude::Command, crate::rule::Preset, rand::prelude::IteratorRandom};
+    use {
+        super::*, crate::prelude::Command, crate::rule::kitty::Kitty, crate::rule::missed_deal::Declare,
+        crate::rule::Preset, rand::prelude::IteratorRandom,
+    };
 
     #[cfg(feature = "server")]
     #[test]
@@ -1193,24 +1647,131 @@ mod test {
 
     #[cfg(feature = "server")]
     #[test]
-    fn next_default_test1() {
+    fn mighty_rule_test() {
+        let mut rule = Rule::from(Preset::Default5);
+        let default_json = serde_json::to_value(&rule).unwrap();
+        assert!(default_json.get("mighty").is_none());
+
+        let state = |giruda| State::SelectFriend {
+            president: 0,
+            giruda,
+            pledge: 13,
+            deck: Vec::new(),
+            kitty: Vec::new(),
+        };
+        let cards = [
+            Card::Normal(Pattern::Spade, 14),
+            Card::Normal(Pattern::Diamond, 14),
+            Card::Normal(Pattern::Heart, 14),
+            Card::Normal(Pattern::Clover, 2),
+        ];
+        let hearts = state(Some(Pattern::Heart));
+        let clovers = state(Some(Pattern::Clover));
+        assert_eq!(hearts.calculate_winner(&rule, &cards), Card::Normal(Pattern::Spade, 14));
+
+        rule.mighty.card = Card::Normal(Pattern::Heart, 14);
+        rule.mighty.alter = Card::Normal(Pattern::Diamond, 14);
+        assert_eq!(rule.validate(), Ok(()));
+        assert!(serde_json::to_value(&rule).unwrap().get("mighty").is_some());
+        assert_eq!(
+            hearts.calculate_winner(&rule, &cards),
+            Card::Normal(Pattern::Diamond, 14)
+        );
+        assert_eq!(
+            clovers.calculate_winner(&rule, &cards),
+            Card::Normal(Pattern::Heart, 14)
+        );
+
+        rule.mighty.enabled = false;
+        assert_eq!(
+            clovers.calculate_winner(&rule, &cards),
+            Card::Normal(Pattern::Clover, 2)
+        );
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn hide_hands_test() {
         let rule = Rule::from(Preset::Default5);
-        let mut state = State::new(&rule);
-        if let Err(x) = state.next(0, Command::Pledge(Some((Some(Pattern::Clover), 12))), &rule) {
-            assert_eq!(format!("{}", x), format!("{}", Error::InvalidPledge(false, 13)))
-        }
-        state = state
-            .next(0, Command::Pledge(Some((Some(Pattern::Clover), 13))), &rule)
-            .unwrap();
-        if let Err(x) = state.next(1, Command::Pledge(Some((Some(Pattern::Clover), 13))), &rule) {
-            assert_eq!(format!("{}", x), format!("{}", Error::InvalidPledge(false, 13)))
+        let state = State::new(&rule).hide_hands(&rule);
+        if let State::Election { deck, left, .. } = state {
+            assert_eq!(deck.len(), 5);
+            assert!(deck.iter().all(|d| d.is_empty()));
+            assert!(left.is_empty());
+        } else {
+            panic!("state should be election");
         }
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn declare_missed_deal_test() {
+        let rule = Rule::from(Preset::Default5);
+        let state = State::new(&rule);
+        assert_eq!(
+            state.next(0, Command::DeclareMissedDeal, &rule),
+            Err(Error::DeclareNotAllowed)
+        );
+
+        let rule = rule.map_missed_deal(|m| m.set_declare(Declare::Voluntary));
+        let missed = (2..=9)
+            .map(|n| Card::Normal(Pattern::Spade, n))
+            .chain((2..=3).map(|n| Card::Normal(Pattern::Heart, n)))
+            .collect::<Vec<_>>();
+        let mut state = State::new(&rule);
+        if let State::Election { deck, curr_user, .. } = &mut state {
+            deck[1] = (10..=14).map(|n| Card::Normal(Pattern::Spade, n)).collect();
+            deck[2] = missed.clone();
+            *curr_user = 3;
+        }
+        assert_eq!(state.valid_users(&rule) & 0b01110, 0b01100);
+        assert_eq!(
+            state.next(1, Command::DeclareMissedDeal, &rule),
+            Err(Error::NotMissedDeal)
+        );
+
+        let pledged = state
+            .next(3, Command::Pledge(Some((Some(Pattern::Spade), 13))), &rule)
+            .unwrap();
+        assert_eq!(
+            pledged.next(2, Command::DeclareMissedDeal, &rule),
+            Err(Error::ElectionStarted)
+        );
+
+        match state.next(2, Command::DeclareMissedDeal, &rule).unwrap() {
+            State::Election {
+                curr_user, revealed, ..
+            } => {
+                assert_eq!(curr_user, 3);
+                assert_eq!(revealed, Some((2, missed)));
+            }
+            _ => panic!("state should be election"),
+        }
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn next_default_test1() {
+        let rule = Rule::from(Preset::Default5);
+        let mut state = State::new(&rule);
+        assert_eq!(
+            state.next(0, Command::Pledge(Some((Some(Pattern::Clover), 12))), &rule),
+            Err(Error::PledgeTooLow(13))
+        );
+        state = state
+            .next(0, Command::Pledge(Some((Some(Pattern::Clover), 13))), &rule)
+            .unwrap();
+        assert_eq!(
+            state.next(1, Command::Pledge(Some((Some(Pattern::Clover), 13))), &rule),
+            Err(Error::PledgeNotIncreasing(13))
+        );
         state = state
             .next(1, Command::Pledge(Some((Some(Pattern::Clover), 14))), &rule)
             .unwrap();
-        if let Err(x) = state.next(1, Command::Pledge(Some((Some(Pattern::Clover), 13))), &rule) {
-            assert_eq!(format!("{}", x), format!("{}", Error::InvalidOrder))
-        }
+        assert_eq!(
+            state.next(1, Command::Pledge(Some((Some(Pattern::Clover), 13))), &rule),
+            Err(Error::NotYourTurn(1))
+        );
         state = state.next(2, Command::Pledge(None), &rule).unwrap();
         state = state.next(3, Command::Pledge(None), &rule).unwrap();
         state = state.next(4, Command::Pledge(None), &rule).unwrap();
@@ -1232,9 +1793,10 @@ mod test {
                 .cloned()
                 .collect();
         }
-        if let Err(x) = state.next(1, Command::ChangePledge(Some(Pattern::Clover)), &rule) {
-            assert_eq!(format!("{}", x), format!("{}", Error::SameGiruda))
-        }
+        assert_eq!(
+            state.next(1, Command::ChangePledge(Some(Pattern::Clover)), &rule),
+            Err(Error::SameGiruda(Some(Pattern::Clover)))
+        );
         state = state
             .next(1, Command::ChangePledge(Some(Pattern::Spade)), &rule)
             .unwrap();
@@ -1270,7 +1832,7 @@ mod test {
             ..
         } = state
         {
-            assert!(winner == 6 || winner == 26);
+            assert!(winner == 0b00110 || winner == 0b11001);
             assert_eq!(president, 1);
             assert_eq!(friend, Some(2));
         }
@@ -1281,16 +1843,18 @@ mod test {
     fn next_gshs5_test1() {
         let rule = Rule::from(Preset::Gshs5);
         let mut state = State::new(&rule);
-        if let Err(x) = state.next(0, Command::Pledge(Some((Some(Pattern::Clover), 13))), &rule) {
-            assert_eq!(format!("{}", x), format!("{}", Error::InvalidPledge(false, 14)))
-        }
+        assert_eq!(
+            state.next(0, Command::Pledge(Some((Some(Pattern::Clover), 13))), &rule),
+            Err(Error::PledgeTooLow(14))
+        );
         state = state.next(0, Command::Pledge(None), &rule).unwrap();
         state = state
             .next(3, Command::Pledge(Some((Some(Pattern::Clover), 14))), &rule)
             .unwrap();
-        if let Err(x) = state.next(2, Command::Pledge(Some((Some(Pattern::Clover), 13))), &rule) {
-            assert_eq!(format!("{}", x), format!("{}", Error::InvalidPledge(false, 13)))
-        }
+        assert_eq!(
+            state.next(2, Command::Pledge(Some((Some(Pattern::Clover), 13))), &rule),
+            Err(Error::PledgeTooLow(14))
+        );
         state = state
             .next(2, Command::Pledge(Some((Some(Pattern::Spade), 14))), &rule)
             .unwrap();
@@ -1312,9 +1876,10 @@ mod test {
                 .choose_multiple(&mut rand::thread_rng(), 4)
                 .cloned()
                 .collect();
-            if let Err(x) = state.next(president, Command::ChangePledge(giruda), &rule) {
-                assert_eq!(format!("{}", x), format!("{}", Error::SameGiruda))
-            }
+            assert_eq!(
+                state.next(president, Command::ChangePledge(giruda), &rule),
+                Err(Error::SameGiruda(giruda))
+            );
             state = state
                 .next(
                     president,
@@ -1346,5 +1911,799 @@ mod test {
             }
         }
     }
+
+    #[cfg(feature = "server")]
+    fn select_friend_state(rule: &Rule) -> State {
+        let cards = rule.deck.0.clone();
+        let mut deck = cards
+            .chunks(rule.card_cnt_per_user as usize)
+            .take(rule.user_cnt as usize)
+            .map(|c| c.to_vec())
+            .collect::<Vec<_>>();
+        let kitty = cards[(rule.user_cnt * rule.card_cnt_per_user) as usize..].to_vec();
+        deck[0].extend_from_slice(&kitty);
+        State::SelectFriend {
+            president: 0,
+            giruda: Some(Pattern::Spade),
+            pledge: 13,
+            deck,
+            kitty,
+        }
+    }
+
+    /// In-game state where the president 0 plays with spade giruda and 13 pledge,
+    /// and `leader` is about to lead the trick of `turn_count`.
+    /// The friend of `FriendFunc::ByUser` is already known.
+    #[cfg(feature = "server")]
+    fn in_game_state(friend_func: FriendFunc, deck: Vec<Vec<Card>>, turn_count: u8, leader: usize) -> State {
+        let friend = match friend_func {
+            FriendFunc::ByUser(u) => Some(u),
+            _ => None,
+        };
+        State::InGame {
+            president: 0,
+            friend_func,
+            friend,
+            is_friend_known: friend.is_some(),
+            giruda: Some(Pattern::Spade),
+            pledge: 13,
+            dropped: Vec::new(),
+            score_deck: vec![Vec::new(); deck.len()],
+            tricks: Vec::new(),
+            turn_count,
+            placed_cards: vec![(Card::Normal(Pattern::Spade, 0), CardPolicy::Valid); deck.len()],
+            deck,
+            start_user: leader,
+            current_user: leader,
+            current_pattern: Rush::from(Pattern::Spade),
+            joker_call_card: None,
+            joker_call_effect: false,
+        }
+    }
+
+    /// Plays the first valid card of the current user.
+    #[cfg(feature = "server")]
+    fn play_first_valid(state: &State, rule: &Rule) -> State {
+        if let State::InGame { deck, current_user, .. } = state {
+            for card in deck[*current_user].iter() {
+                for pat in [Pattern::Spade, Pattern::Diamond, Pattern::Heart, Pattern::Clover].iter() {
+                    if let Ok(next) = state.next(*current_user, Command::Go(*card, Rush::from(*pat), false), rule) {
+                        return next;
+                    }
+                }
+            }
+        }
+        panic!("no valid card in {:?}", state);
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn friend_func_flag_test() {
+        let funcs = vec![
+            (
+                friend::Friend::CARD,
+                FriendFunc::ByCard(Card::Normal(Pattern::Heart, 14)),
+            ),
+            (friend::Friend::PICK, FriendFunc::ByUser(1)),
+            (friend::Friend::FIRST, FriendFunc::First),
+            (friend::Friend::LAST, FriendFunc::Last),
+            (friend::Friend::NONE, FriendFunc::None),
+        ];
+        for (flag, func) in funcs {
+            let mut rule = Rule::from(Preset::Default5);
+            let state = select_friend_state(&rule);
+            let drop_card = if let State::SelectFriend { deck, .. } = &state {
+                deck[0][..3].to_vec()
+            } else {
+                unreachable!()
+            };
+            let select = |rule: &Rule| state.next(0, Command::SelectFriend(drop_card.clone(), func.clone()), rule);
+
+            assert!(select(&rule).is_ok(), "{:?} should be allowed", func);
+            rule.friend = friend::Friend::all() - flag;
+            assert_eq!(
+                select(&rule),
+                Err(Error::FriendNotAllowed(func.clone())),
+                "{:?} should be rejected",
+                func
+            );
+        }
+
+        let mut rule = Rule::from(Preset::Default5);
+        let state = select_friend_state(&rule);
+        if let State::SelectFriend { deck, .. } = &state {
+            let drop_card = deck[0][..3].to_vec();
+            for user in [0, 5, 100].iter() {
+                assert_eq!(
+                    state.next(
+                        0,
+                        Command::SelectFriend(drop_card.clone(), FriendFunc::ByUser(*user)),
+                        &rule
+                    ),
+                    Err(Error::InvalidFriend(*user))
+                );
+            }
+            let own_card = deck[0][5];
+            assert!(state
+                .next(
+                    0,
+                    Command::SelectFriend(drop_card.clone(), FriendFunc::ByCard(own_card)),
+                    &rule
+                )
+                .is_ok());
+            rule.friend -= friend::Friend::FAKE;
+            assert_eq!(
+                state.next(0, Command::SelectFriend(drop_card, FriendFunc::ByCard(own_card)), &rule),
+                Err(Error::FakeFriend(own_card))
+            );
+        }
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn select_friend_turn_test() {
+        let rule = Rule::from(Preset::Default5);
+        let state = select_friend_state(&rule);
+        for user in 1..5 {
+            let drop_card = match &state {
+                State::SelectFriend { deck, .. } => deck[user][..3].to_vec(),
+                _ => unreachable!(),
+            };
+            assert_eq!(
+                state.next(user, Command::SelectFriend(drop_card, FriendFunc::None), &rule),
+                Err(Error::NotYourTurn(user))
+            );
+            assert_eq!(
+                state.next(user, Command::ChangePledge(None), &rule),
+                Err(Error::NotYourTurn(user))
+            );
+        }
+        assert_eq!(
+            state.next(0, Command::SelectFriend(Vec::new(), FriendFunc::None), &rule),
+            Err(Error::DropCard(3, 0))
+        );
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn friend_reveal_test() {
+        for reveal in [FriendReveal::OnPlay, FriendReveal::FirstTrick, FriendReveal::End].iter() {
+            let mut rule = Rule::from(Preset::Default5);
+            rule.friend_reveal = *reveal;
+            let state = select_friend_state(&rule);
+            let (drop_card, friend_card) = if let State::SelectFriend { deck, .. } = &state {
+                (deck[0][..3].to_vec(), deck[1][9])
+            } else {
+                unreachable!()
+            };
+            let funcs = vec![
+                FriendFunc::ByCard(friend_card),
+                FriendFunc::ByUser(2),
+                FriendFunc::First,
+                FriendFunc::Last,
+                FriendFunc::None,
+            ];
+
+            for func in funcs {
+                let mut state = state
+                    .next(0, Command::SelectFriend(drop_card.clone(), func.clone()), &rule)
+                    .unwrap();
+                let mut card_played = false;
+                while let State::InGame {
+                    is_friend_known,
+                    turn_count,
+                    placed_cards,
+                    ..
+                } = &state
+                {
+                    card_played |= placed_cards.iter().any(|(c, _)| *c == friend_card);
+                    let expected = match (reveal, &func) {
+                        (_, FriendFunc::None) => true,
+                        (_, FriendFunc::Last) | (FriendReveal::End, _) => false,
+                        (FriendReveal::OnPlay, FriendFunc::ByUser(_)) => true,
+                        (FriendReveal::OnPlay, FriendFunc::ByCard(_)) => card_played,
+                        _ => *turn_count > 0,
+                    };
+                    assert_eq!(
+                        *is_friend_known, expected,
+                        "{:?} {:?} at turn {}",
+                        reveal, func, turn_count
+                    );
+                    state = play_first_valid(&state, &rule);
+                }
+
+                if let State::GameEnded { friend, .. } = state {
+                    match func {
+                        FriendFunc::ByCard(_) => assert_eq!(friend, Some(1)),
+                        FriendFunc::ByUser(u) => assert_eq!(friend, Some(u)),
+                        FriendFunc::None => assert_eq!(friend, None),
+                        _ => {}
+                    }
+                } else {
+                    panic!("game should be ended");
+                }
+            }
+        }
+    }
+    #[cfg(feature = "server")]
+    #[test]
+    fn legal_commands_test() {
+        let rule = Rule::from(Preset::Default5);
+        let state = State::new(&rule);
+        let curr_user = (0..5).find(|u| state.valid_users(&rule) & (1 << u) != 0).unwrap();
+        let commands = state.legal_commands(curr_user, &rule);
+        assert!(commands.contains(&Command::Pledge(Some((Some(Pattern::Spade), rule.pledge.min)))));
+        assert!(!commands.contains(&Command::Pledge(Some((Some(Pattern::Spade), rule.pledge.min - 1)))));
+        assert!(state.legal_commands((curr_user + 1) % 5, &rule).is_empty());
+
+        let state = select_friend_state(&rule);
+        let commands = state.legal_commands(0, &rule);
+        assert!(commands.contains(&Command::ChangePledge(Some(Pattern::Heart))));
+        assert!(!commands.contains(&Command::ChangePledge(Some(Pattern::Spade))));
+        assert!(commands
+            .iter()
+            .any(|c| matches!(c, Command::SelectFriend(d, FriendFunc::ByUser(1)) if d.len() == 3)));
+        assert!(!commands
+            .iter()
+            .any(|c| matches!(c, Command::SelectFriend(_, FriendFunc::ByUser(0)))));
+
+        let (count, cards) = state.drop_options(0, &rule).unwrap();
+        assert_eq!((count, cards.len()), (3, 13));
+        assert_eq!(state.drop_options(1, &rule), None);
+
+        let rule = rule.set_friend(friend::Friend::CARD);
+        let commands = select_friend_state(&rule).legal_commands(0, &rule);
+        assert!(commands.iter().all(|c| matches!(
+            c,
+            Command::ChangePledge(_) | Command::SelectFriend(_, FriendFunc::ByCard(_))
+        )));
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn random_command_test() {
+        for preset in [Preset::Default5, Preset::Gshs5, Preset::Dhsh5].iter() {
+            let rule = Rule::from(*preset);
+            let mut state = State::new(&rule);
+            for _ in 0..500 {
+                if let State::GameEnded { .. } = state {
+                    break;
+                }
+                let user = (0..5)
+                    .filter(|u| state.valid_users(&rule) & (1 << u) != 0)
+                    .choose(&mut rand::thread_rng())
+                    .unwrap();
+                if let State::InGame { .. } = state {
+                    assert!(
+                        !state.legal_commands(user, &rule).is_empty(),
+                        "{:?} {:?}",
+                        preset,
+                        state
+                    );
+                }
+                state = state.next(user, Command::Random, &rule).unwrap();
+            }
+            assert_eq!(state.phase(), Phase::GameEnded, "{:?}", preset);
+        }
+    }
+
+    #[cfg(feature = "server")]
+    fn sorted(cards: impl IntoIterator<Item = Card>) -> Vec<Card> {
+        let mut cards = cards.into_iter().collect::<Vec<_>>();
+        cards.sort();
+        cards
+    }
+
+    /// Checks that the tricks are the played cards in order, led by the winner of the last trick,
+    /// and their score cards are in the score deck of the winner.
+    #[cfg(feature = "server")]
+    fn check_tricks(tricks: &[Trick], score_deck: &[Vec<Card>], played: &[Card]) {
+        let cards = tricks.iter().flat_map(|t| t.cards.iter().map(|(c, _)| *c));
+        assert!(cards.eq(played[..tricks.len() * score_deck.len()].iter().copied()));
+        for (prev, next) in tricks.iter().zip(tricks.iter().skip(1)) {
+            assert_eq!(prev.winner, next.leader);
+        }
+        for (user, cards) in score_deck.iter().enumerate() {
+            let won = tricks
+                .iter()
+                .filter(|t| t.winner == user)
+                .flat_map(|t| t.cards.iter().map(|(c, _)| *c).filter(Card::is_score));
+            assert_eq!(sorted(won), sorted(cards.iter().copied()), "score deck of {}", user);
+        }
+    }
+
+    /// Plays a game with `Command::Random` from random users, checking the invariants on every state.
+    ///
+    /// - cards in hands, left cards, dropped cards and played cards are always the deck
+    /// - score cards are the score cards of the finished tricks, taken by their winners
+    /// - there are exactly `card_cnt_per_user` tricks
+    /// - `valid_users` is not zero before `GameEnded`
+    /// - winners are the ruling party (president and friend) or the others
+    /// - points of the ruling party are their score cards and the kitty points
+    #[cfg(feature = "server")]
+    fn play_random_game(rule: &Rule) {
+        let mut rng = rand::thread_rng();
+        let seat_cnt = rule.seat_cnt();
+        let card_cnt = rule.card_cnt_per_user as usize;
+        let all_cards = sorted(rule.deck.0.iter().copied());
+        let mut state = State::new(rule);
+        let mut dropped = Vec::new();
+        let mut played = Vec::new();
+        let mut tricks = 0;
+
+        for _ in 0..10000 {
+            let valid_users = state.valid_users(rule);
+            assert_ne!(valid_users, 0, "no one can play in {:?}", state);
+            let user = (0..seat_cnt)
+                .filter(|u| valid_users & (1 << u) != 0)
+                .choose(&mut rng)
+                .unwrap();
+            let seat = state.seat(user, rule);
+            let next = state
+                .next(user, Command::Random, rule)
+                .unwrap_or_else(|e| panic!("{:?} from user {} in {:?}", e, user, state));
+
+            match (&state, &next) {
+                (State::SelectFriend { deck, .. }, State::InGame { deck: next_deck, .. }) => {
+                    dropped = State::remove_cards(&deck[user], &next_deck[user]);
+                    assert_eq!(dropped.len(), rule.kitty_size());
+                }
+                (
+                    State::InGame { deck, turn_count, .. },
+                    State::InGame {
+                        deck: next_deck,
+                        turn_count: next_turn_count,
+                        ..
+                    },
+                ) => {
+                    let card = State::remove_cards(&deck[seat], &next_deck[seat]);
+                    assert_eq!(card.len(), 1);
+                    played.extend(card);
+                    assert!(*next_turn_count == *turn_count || *next_turn_count == *turn_count + 1);
+                    tricks += (next_turn_count - turn_count) as usize;
+                }
+                (State::InGame { deck, .. }, State::GameEnded { .. }) => {
+                    assert_eq!(deck[seat].len(), 1);
+                    played.extend(deck[seat].iter().copied());
+                    tricks += 1;
+                }
+                _ => {}
+            }
+            state = next;
+
+            match &state {
+                State::Election { deck, left, .. } => {
+                    assert_eq!(sorted(deck.iter().flatten().chain(left.iter()).copied()), all_cards);
+                }
+                State::SelectFriend { deck, .. } => {
+                    assert_eq!(sorted(deck.iter().flatten().copied()), all_cards);
+                }
+                State::InGame {
+                    deck,
+                    dropped: dropped_cards,
+                    score_deck,
+                    tricks: finished_tricks,
+                    turn_count,
+                    ..
+                } => {
+                    assert_eq!(finished_tricks.len(), *turn_count as usize);
+                    check_tricks(finished_tricks, score_deck, &played);
+                    assert_eq!(sorted(dropped_cards.iter().copied()), sorted(dropped.iter().copied()));
+                    let cards = deck.iter().flatten().chain(played.iter()).chain(dropped.iter());
+                    assert_eq!(sorted(cards.copied()), all_cards);
+                    let finished = &played[..*turn_count as usize * seat_cnt];
+                    assert_eq!(
+                        sorted(score_deck.iter().flatten().copied()),
+                        sorted(finished.iter().copied().filter(Card::is_score))
+                    );
+                }
+                State::GameEnded {
+                    winner,
+                    president,
+                    friend,
+                    scores,
+                    score_deck,
+                    dropped: dropped_cards,
+                    points,
+                    tricks: finished_tricks,
+                    ..
+                } => {
+                    assert_eq!(finished_tricks.len(), card_cnt);
+                    check_tricks(finished_tricks, score_deck, &played);
+                    assert_eq!(tricks, card_cnt);
+                    assert_eq!(played.len(), seat_cnt * card_cnt);
+                    assert_eq!(sorted(played.iter().chain(dropped.iter()).copied()), all_cards);
+                    assert_eq!(
+                        played.iter().chain(dropped.iter()).filter(|c| c.is_score()).count(),
+                        all_cards.iter().filter(|c| c.is_score()).count()
+                    );
+                    let ruling = friend.map_or(0, |f| 1 << f) | 1 << president | rule.dummy_seats();
+                    let users = (1 << rule.user_cnt) - 1;
+                    assert!(
+                        *winner == ruling & users || *winner == !ruling & users,
+                        "winner {:b} with president {} and friend {:?}",
+                        winner,
+                        president,
+                        friend
+                    );
+                    assert_eq!(sorted(dropped_cards.iter().copied()), sorted(dropped.iter().copied()));
+                    assert_eq!(
+                        sorted(score_deck.iter().flatten().copied()),
+                        sorted(played.iter().copied().filter(Card::is_score))
+                    );
+                    let dropped_score = dropped.iter().filter(|c| c.is_score()).count();
+                    let captured = (0..seat_cnt)
+                        .filter(|i| ruling & 1 << i != 0)
+                        .map(|i| score_deck[i].len())
+                        .sum::<usize>();
+                    match rule.kitty.points {
+                        KittyPoints::Ruling => assert_eq!(*points as usize, captured + dropped_score),
+                        _ => assert_eq!(*points as usize, captured),
+                    }
+                    assert_eq!(scores.len(), rule.user_cnt as usize);
+                    for (i, score) in scores.iter().enumerate() {
+                        assert_eq!(
+                            *score > 0,
+                            winner & 1 << i != 0,
+                            "{:?} with winner {:b}",
+                            scores,
+                            winner
+                        );
+                    }
+                    return;
+                }
+            }
+        }
+        panic!("game didn't end: {:?}", state);
+    }
+
+    #[cfg(feature = "server")]
+    fn play_random_games(games: usize) {
+        let presets = [
+            Preset::Default5,
+            Preset::Ddshs5,
+            Preset::Dhsh5,
+            Preset::Kmla5,
+            Preset::Gsa5,
+            Preset::Gshs5,
+            Preset::Skku5,
+            Preset::Sshs5,
+            Preset::Yu5,
+            Preset::Double10,
+            Preset::Practice3,
+            Preset::Practice2,
+        ];
+        for preset in presets.iter() {
+            let rule = Rule::from(*preset);
+            for _ in 0..games {
+                play_random_game(&rule);
+            }
+        }
+        let kitties = [
+            Kitty::new().set_max_exchange(Some(0)),
+            Kitty::new()
+                .set_max_exchange(Some(1))
+                .set_points(KittyPoints::Opposition),
+            Kitty::new()
+                .set_reveal(KittyReveal::All)
+                .set_points(KittyPoints::Nobody),
+        ];
+        for kitty in kitties.iter() {
+            let rule = Rule::from(Preset::Default5).set_kitty(kitty.clone());
+            for _ in 0..games {
+                play_random_game(&rule);
+            }
+        }
+        let rule = Rule::from(Preset::Double10).set_tie_break(TieBreak::Last);
+        for _ in 0..games {
+            play_random_game(&rule);
+        }
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn random_game_invariant_test() {
+        play_random_games(100);
+    }
+
+    /// Run with `cargo test --release --features server -- --ignored`
+    #[cfg(feature = "server")]
+    #[test]
+    #[ignore]
+    fn random_game_invariant_long_test() {
+        play_random_games(5000);
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn kitty_test() {
+        let rule = Rule::from(Preset::Default5).map_kitty(|k| k.set_max_exchange(Some(1)));
+        let state = select_friend_state(&rule);
+        let (hand, kitty) = match &state {
+            State::SelectFriend { deck, kitty, .. } => (deck[0].clone(), kitty.clone()),
+            _ => unreachable!(),
+        };
+        assert_eq!(kitty.len(), rule.kitty_size());
+
+        // keeps 2 cards of the kitty
+        let drop_card = vec![kitty[0], hand[0], hand[1]];
+        assert_eq!(
+            state.next(0, Command::SelectFriend(drop_card, FriendFunc::None), &rule),
+            Err(Error::KittyExchange(1))
+        );
+        let drop_card = vec![kitty[0], kitty[1], hand[0]];
+        match state.next(0, Command::SelectFriend(drop_card.clone(), FriendFunc::None), &rule) {
+            Ok(State::InGame { dropped, .. }) => assert_eq!(dropped, drop_card),
+            x => panic!("{:?}", x),
+        }
+
+        assert!(matches!(state.hide_hands(&rule), State::SelectFriend { kitty, .. } if kitty.is_empty()));
+        let rule = rule.map_kitty(|k| k.set_reveal(KittyReveal::All));
+        assert!(matches!(state.hide_hands(&rule), State::SelectFriend { kitty: k, .. } if k == kitty));
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn kitty_points_test() {
+        let cards = |p: Pattern, n: std::ops::RangeInclusive<u8>| n.map(move |n| Card::Normal(p, n));
+        // ruling party (0 and 1) has 12 points, opposition has 5 points, and 2 points are dropped
+        let deck = (2..=6).rev().map(|n| vec![Card::Normal(Pattern::Clover, n)]).collect();
+        let mut state = in_game_state(FriendFunc::ByUser(1), deck, 9, 0);
+        if let State::InGame {
+            dropped, score_deck, ..
+        } = &mut state
+        {
+            *dropped = vec![
+                Card::Normal(Pattern::Heart, 10),
+                Card::Normal(Pattern::Heart, 11),
+                Card::Normal(Pattern::Heart, 2),
+            ];
+            *score_deck = vec![
+                cards(Pattern::Spade, 10..=14)
+                    .chain(cards(Pattern::Diamond, 12..=14))
+                    .collect(),
+                cards(Pattern::Diamond, 10..=11)
+                    .chain(cards(Pattern::Heart, 12..=13))
+                    .collect(),
+                cards(Pattern::Clover, 10..=12).collect(),
+                cards(Pattern::Clover, 13..=14).collect(),
+                Vec::new(),
+            ];
+        }
+        let results = [
+            (KittyPoints::Ruling, 0b00011, vec![8, 4, -4, -4, -4]),
+            (KittyPoints::Opposition, 0b11100, vec![-2, -1, 1, 1, 1]),
+            (KittyPoints::Nobody, 0b11100, vec![-2, -1, 1, 1, 1]),
+        ];
+        for (points, expected_winner, expected_scores) in results.iter() {
+            let rule = Rule::from(Preset::Default5).map_kitty(|k| k.set_points(*points));
+            let mut state = state.clone();
+            for user in 0..5 {
+                state = play_first_valid(&state, &rule);
+                assert_eq!(state.valid_users(&rule) == 0, user == 4);
+            }
+            match state {
+                State::GameEnded { winner, scores, .. } => {
+                    assert_eq!((winner, &scores), (*expected_winner, expected_scores), "{:?}", points)
+                }
+                x => panic!("{:?}", x),
+            }
+        }
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn trick_history_test() {
+        let rule = Rule::from(Preset::Default5);
+        let deck = (10..=14).map(|n| vec![Card::Normal(Pattern::Clover, n)]).collect();
+        let mut state = in_game_state(FriendFunc::ByUser(1), deck, 8, 3);
+        for _ in 0..5 {
+            state = play_first_valid(&state, &rule);
+        }
+        match state {
+            State::InGame {
+                tricks,
+                score_deck,
+                start_user,
+                ..
+            } => {
+                let cards = [3, 4, 0, 1, 2]
+                    .iter()
+                    .map(|i| (Card::Normal(Pattern::Clover, 10 + i), CardPolicy::Valid))
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    tricks,
+                    vec![Trick {
+                        leader: 3,
+                        cards,
+                        rush: Rush::from(Pattern::Clover),
+                        joker_call: false,
+                        winner: 4,
+                    }]
+                );
+                assert_eq!(start_user, 4);
+                assert_eq!(score_deck[4].len(), 5);
+            }
+            x => panic!("{:?}", x),
+        }
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn joker_call_mighty_defense_test() {
+        let rule = Rule::from(Preset::Default5);
+        let deck = ["C3 C4 C5", "DA JK-B H5", "C6 C7 C8", "C9 C10 H2", "CJ H3 H4"]
+            .iter()
+            .map(|h| Card::parse_hand(h).unwrap())
+            .collect();
+        let mut state = in_game_state(FriendFunc::None, deck, 1, 0);
+        let go = |s: &str, joker_call: bool| {
+            let card = s.parse().unwrap();
+            Command::Go(card, Rush::from(card), joker_call)
+        };
+        state = state.next(0, go("C3", true), &rule).unwrap();
+        assert_eq!(
+            state.next(1, go("H5", false), &rule),
+            Err(Error::JokerCall(Card::Normal(Pattern::Clover, 3)))
+        );
+        // the mighty is placed instead of the called joker, and takes the trick
+        state = state.next(1, go("DA", false), &rule).unwrap();
+        for (user, card) in [(2, "C6"), (3, "C9"), (4, "CJ")].iter() {
+            state = state.next(*user, go(card, false), &rule).unwrap();
+        }
+        assert!(matches!(state, State::InGame { start_user: 1, .. }));
+        assert!(state.legal_commands(1, &rule).contains(&go("H5", false)));
+        assert!(state.next(1, go("H5", false), &rule).is_ok());
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn duplicate_card_test() {
+        let numbers = [2, 4, 5, 13, 6, 14, 7, 14, 8, 9];
+        let deck = numbers
+            .iter()
+            .map(|n| vec![Card::Normal(Pattern::Clover, *n)])
+            .collect();
+        let state = in_game_state(FriendFunc::ByCard(Card::Normal(Pattern::Clover, 13)), deck, 8, 0);
+        for (tie_break, expected_winner) in [(TieBreak::First, 5), (TieBreak::Last, 7)].iter() {
+            let rule = Rule::from(Preset::Double10).set_tie_break(*tie_break);
+            let mut state = state.clone();
+            for _ in 0..10 {
+                state = play_first_valid(&state, &rule);
+            }
+            match state {
+                State::InGame {
+                    friend,
+                    is_friend_known,
+                    tricks,
+                    ..
+                } => {
+                    assert_eq!((friend, is_friend_known), (Some(3), true));
+                    assert_eq!(tricks[0].winner, *expected_winner, "{:?}", tie_break);
+                }
+                x => panic!("{:?}", x),
+            }
+        }
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn dummy_hand_test() {
+        let rule = Rule::from(Preset::Practice3);
+        let deck = (2..=5).map(|n| vec![Card::Normal(Pattern::Clover, n)]).collect();
+        let state = in_game_state(FriendFunc::None, deck, 10, 3);
+        let dummy_card = Card::Normal(Pattern::Clover, 5);
+        assert_eq!(state.valid_users(&rule), 0b0001);
+        assert_eq!(
+            state.legal_commands(0, &rule),
+            vec![Command::Go(dummy_card, Rush::from(Pattern::Clover), false)]
+        );
+        match state
+            .next(0, Command::Go(dummy_card, Rush::empty(), false), &rule)
+            .unwrap()
+        {
+            State::InGame { deck, current_user, .. } => {
+                assert!(deck[3].is_empty());
+                assert_eq!(deck[0].len(), 1);
+                assert_eq!(current_user, 0);
+            }
+            x => panic!("{:?}", x),
+        }
+
+        match state.hide_hands(&rule) {
+            State::InGame { deck, .. } => assert_eq!(deck, vec![vec![], vec![], vec![], vec![dummy_card]]),
+            x => panic!("{:?}", x),
+        }
+        let rule = rule.map_table(|t| t.set_open_hand(true));
+        assert!(matches!(state.hide_hands(&rule), State::InGame { deck, .. } if deck.iter().all(|d| d.len() == 1)));
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn dummy_scoring_test() {
+        let rule = Rule::from(Preset::Practice3);
+        let deck = (2..=5).map(|n| vec![Card::Normal(Pattern::Clover, n)]).collect();
+        let mut state = in_game_state(FriendFunc::None, deck, rule.last_turn(), 0);
+        if let State::InGame { score_deck, .. } = &mut state {
+            let points = |n: usize| vec![Card::Normal(Pattern::Heart, 10); n];
+            *score_deck = vec![points(8), points(3), points(3), points(6)];
+        }
+        for user in 0..3 {
+            let card = Card::Normal(Pattern::Clover, user as u8 + 2);
+            state = state
+                .next(user, Command::Go(card, Rush::empty(), false), &rule)
+                .unwrap();
+        }
+        let dummy_card = Card::Normal(Pattern::Clover, 5);
+        match state
+            .next(0, Command::Go(dummy_card, Rush::empty(), false), &rule)
+            .unwrap()
+        {
+            State::GameEnded {
+                winner, points, scores, ..
+            } => {
+                assert_eq!(points, 14);
+                assert_eq!(winner, 0b001);
+                assert_eq!(scores.len(), 3);
+                assert!(scores[0] > 0 && scores[1] < 0 && scores[2] < 0);
+            }
+            x => panic!("{:?}", x),
+        }
+
+        let kitty = (2..2 + rule.kitty_size() as u8)
+            .map(|n| Card::Normal(Pattern::Diamond, n))
+            .collect::<Vec<_>>();
+        let mut deck = vec![Vec::new(); rule.seat_cnt()];
+        deck[0] = kitty.clone();
+        let state = State::SelectFriend {
+            president: 0,
+            giruda: Some(Pattern::Spade),
+            pledge: 13,
+            deck,
+            kitty: kitty.clone(),
+        };
+        assert_eq!(
+            state.next(0, Command::SelectFriend(kitty, FriendFunc::ByUser(3)), &rule),
+            Err(Error::InvalidFriend(3))
+        );
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn seeded_deal_test() {
+        let rule = Rule::from(Preset::Default5);
+        assert_eq!(State::from_seed(&rule, 42), State::from_seed(&rule, 42));
+        assert_ne!(State::from_seed(&rule, 42), State::from_seed(&rule, 43));
+        match State::from_seed(&rule, 42) {
+            State::Election { deck, left, .. } => {
+                let mut cards = deck.concat();
+                cards.extend(left);
+                cards.sort();
+                let mut expected = rule.deck.0.clone();
+                expected.sort();
+                assert_eq!(cards, expected);
+            }
+            x => panic!("{:?}", x),
+        }
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn seeded_game_test() {
+        let rule = Rule::from(Preset::Default5);
+        let play = |seed: u64| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut state = State::from_rng(&rule, &mut rng);
+            while state.phase() != Phase::GameEnded {
+                let user = state.valid_users(&rule).trailing_zeros() as usize;
+                state = state.next_with_rng(user, Command::Random, &rule, &mut rng).unwrap();
+            }
+            state
+        };
+        for seed in 0..5 {
+            assert_eq!(play(seed), play(seed));
+        }
+    }
+
     // not random and real data test should be applied
 }
@@ -1,9 +1,13 @@
 use crate::actor::room::Room;
-use crate::actor::user::User;
+use crate::actor::session::Session;
+use crate::actor::user::{GotDirectMessage, User};
+use crate::actor::Main;
+use crate::chat::{now_millis, ChatConfig};
 use crate::db::game::{save_rule, SaveRuleForm};
 use crate::db::user::{get_user_info, GetInfoForm};
 use crate::dev::*;
 use actix::prelude::*;
+use mighty::error::RuleError;
 use mighty::prelude::Rule;
 use rand::distributions::{Distribution, Uniform};
 use rand::thread_rng;
@@ -19,6 +23,7 @@ pub struct Hub {
     counter: u64,
     users: HashMap<UserNo, Addr<User>>,
     pool: Pool,
+    chat: ChatConfig,
 }
 
 impl Actor for Hub {
@@ -41,14 +46,20 @@ impl Handler<GetRoom> for Hub {
 
 /// This would make room with `room_name`, `rule`, and `is_rank`.
 /// The `room_id` would generated with random value.
+/// Observers can't see the hands in rank room by default.
+/// Returns every violated constraint if the rule is invalid.
+///
+/// No service sends this yet, since rooms can't be created from the client.
+/// The errors are meant to be shown like `invalid_rule` of the room, which the client checks with `validate_rule`.
 #[derive(Debug, Clone, Message)]
-#[rtype(result = "RoomId")]
+#[rtype(result = "Result<RoomId, Vec<RuleError>>")]
 pub struct MakeRoom(pub String, pub Rule, pub bool);
 
 impl Handler<MakeRoom> for Hub {
-    type Result = RoomId;
+    type Result = Result<RoomId, Vec<RuleError>>;
 
     fn handle(&mut self, msg: MakeRoom, ctx: &mut Self::Context) -> Self::Result {
+        msg.1.validate()?;
         let room_uuid = RoomUid::generate_random();
         let room_id = self.generate_room_id();
         let user_cnt = msg.1.user_cnt as usize;
@@ -65,13 +76,20 @@ impl Handler<MakeRoom> for Hub {
                 user: vec![UserNo(0); user_cnt],
                 observer_cnt: 0,
                 is_game: false,
+                spectate: if msg.2 {
+                    SpectateMode::HideHands
+                } else {
+                    SpectateMode::Live
+                },
+                allow_undo: false,
             },
             ctx.address(),
             self.pool.clone(),
+            self.chat.clone(),
         )
         .start();
         self.room.insert(room_id, room);
-        room_id
+        Ok(room_id)
     }
 }
 
@@ -122,6 +140,47 @@ impl Handler<GetUser> for Hub {
     }
 }
 
+/// Sends direct message from the user to the other user.
+/// It is rejected when the receiver is not online or the message is invalid.
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct SendDirectMessage(pub UserNo, pub UserNo, pub String, pub Addr<Session<Main>>);
+
+impl Handler<SendDirectMessage> for Hub {
+    type Result = ();
+
+    fn handle(&mut self, msg: SendDirectMessage, _: &mut Self::Context) -> Self::Result {
+        let SendDirectMessage(from, to, content, addr) = msg;
+        let checked = match self.users.get(&to) {
+            Some(_) if from == to => Err(("invalid_user", "you can't send message to yourself".to_owned())),
+            Some(_) => self.chat.check(&content),
+            None => Err(("user_offline", "the user is not online".to_owned())),
+        };
+        let content = match checked {
+            Ok(content) => content,
+            Err((code, message)) => {
+                addr.do_send(MainToClient::Error {
+                    code: code.to_owned(),
+                    message,
+                });
+                return;
+            }
+        };
+
+        let dm = DirectMessage {
+            from,
+            to,
+            content,
+            time: now_millis(),
+        };
+        for user in [from, to].iter() {
+            if let Some(addr) = self.users.get(user) {
+                addr.do_send(GotDirectMessage(dm.clone()));
+            }
+        }
+    }
+}
+
 /// When user gets offline, this would remove user.
 #[derive(Debug, Clone, Message)]
 #[rtype(result = "()")]
@@ -136,12 +195,13 @@ impl Handler<HubDisconnect> for Hub {
 }
 
 impl Hub {
-    pub fn new(pool: Pool) -> Hub {
+    pub fn new(pool: Pool, chat: ChatConfig) -> Hub {
         Hub {
             room: HashMap::new(),
             counter: 0,
             users: HashMap::new(),
             pool,
+            chat,
         }
     }
 
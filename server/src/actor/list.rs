@@ -4,6 +4,7 @@ use crate::actor::session::{Session, SessionTrait};
 use crate::actor::Hub;
 use crate::db::game::{get_room_list, GetRoomListForm};
 use crate::dev::*;
+use crate::rate_limit::LimitKind;
 use actix::prelude::*;
 use actix_web_actors::ws::WebsocketContext;
 use types::{ListToClient, ListToServer};
@@ -18,7 +19,7 @@ impl SessionTrait for List {
     type Sender = ListToClient;
 
     fn receive(act: &mut Session<Self>, msg: String, ctx: &mut WebsocketContext<Session<Self>>) {
-        let msg: ListToServer = ignore!(serde_json::from_str(&*msg));
+        let msg: ListToServer = ignore!(act.parse(&*msg, ctx));
         match msg {
             ListToServer::Subscribe(id) => {
                 act.inner
@@ -49,6 +50,9 @@ impl SessionTrait for List {
                     .wait(ctx);
             }
             ListToServer::GetRoomList { user_num } => {
+                if !act.limit(LimitKind::RoomList, ctx) {
+                    return;
+                }
                 let form = GetRoomListForm { user_num };
                 let room_list = ignore!(get_room_list(&form, act.inner.pool.clone()));
                 ctx.notify(ListToClient::RoomList(room_list));
@@ -1,13 +1,15 @@
-use crate::actor::hub::GetUser;
+use crate::actor::hub::{GetUser, SendDirectMessage};
 use crate::actor::session::{Session, SessionTrait};
 use crate::actor::user::{Update, UserConnect, UserDisconnect};
 use crate::actor::{Hub, User};
+use crate::dev::*;
+use crate::rate_limit::LimitKind;
 use actix::prelude::*;
 use actix_web_actors::ws::WebsocketContext;
-use types::{MainToClient, MainToServer};
 
 #[derive(Debug)]
 pub struct Main {
+    no: UserNo,
     user: Addr<User>,
     hub: Addr<Hub>,
 }
@@ -15,16 +17,23 @@ pub struct Main {
 impl SessionTrait for Main {
     type Sender = MainToClient;
 
-    fn started(act: &mut Session<Self>, _: &mut WebsocketContext<Session<Self>>) {
-        act.inner.user.do_send(UserConnect::Main);
+    fn started(act: &mut Session<Self>, ctx: &mut WebsocketContext<Session<Self>>) {
+        act.inner.user.do_send(UserConnect::Main(ctx.address()));
     }
 
-    fn stopped(act: &mut Session<Self>, _: &mut WebsocketContext<Session<Self>>) {
-        act.inner.user.do_send(UserDisconnect::Main);
+    fn stopped(act: &mut Session<Self>, ctx: &mut WebsocketContext<Session<Self>>) {
+        act.inner.user.do_send(UserDisconnect::Main(ctx.address()));
     }
 
     fn receive(act: &mut Session<Self>, msg: String, ctx: &mut WebsocketContext<Session<Self>>) {
-        let msg: MainToServer = serde_json::from_str(&*msg).unwrap();
+        let msg: MainToServer = ignore!(act.parse(&*msg, ctx));
+        let kind = match msg {
+            MainToServer::DirectMessage(..) => LimitKind::Chat,
+            _ => LimitKind::Command,
+        };
+        if !act.limit(kind, ctx) {
+            return;
+        }
         match msg {
             MainToServer::Subscribe(no) => {
                 act.inner
@@ -57,12 +66,24 @@ impl SessionTrait for Main {
             MainToServer::Update => {
                 act.inner.user.do_send(Update);
             }
+            MainToServer::DirectMessage(to, content) => {
+                act.inner
+                    .hub
+                    .do_send(SendDirectMessage(act.inner.no, to, content, ctx.address()));
+            }
         }
     }
+
+    fn error(code: &str, message: String) -> Option<Self::Sender> {
+        Some(MainToClient::Error {
+            code: code.to_owned(),
+            message,
+        })
+    }
 }
 
 impl Main {
-    pub fn new(user: Addr<User>, hub: Addr<Hub>) -> Main {
-        Main { user, hub }
+    pub fn new(no: UserNo, user: Addr<User>, hub: Addr<Hub>) -> Main {
+        Main { no, user, hub }
     }
 }
@@ -1,14 +1,16 @@
-use crate::actor::room::{Chat, RoomJoin, RoomLeave};
+use crate::actor::room::{Chat, ChatAction, Moderate, RoomJoin, RoomLeave};
 use crate::actor::session::{Session, SessionTrait};
 use crate::actor::Room;
+use crate::dev::*;
+use crate::rate_limit::LimitKind;
 use actix::prelude::*;
 use actix_web_actors::ws::WebsocketContext;
-use types::{ObserveToClient, ObserveToServer, UserNo};
 
 #[derive(Debug)]
 pub struct Observe {
     room: Addr<Room>,
     no: UserNo,
+    is_admin: bool,
 }
 
 impl SessionTrait for Observe {
@@ -22,16 +24,36 @@ impl SessionTrait for Observe {
         act.inner.room.do_send(RoomLeave::Observe(ctx.address()));
     }
 
-    fn receive(act: &mut Session<Self>, msg: String, _: &mut WebsocketContext<Session<Self>>) {
-        let msg: ObserveToServer = serde_json::from_str(&*msg).unwrap();
+    fn receive(act: &mut Session<Self>, msg: String, ctx: &mut WebsocketContext<Session<Self>>) {
+        let msg: ObserveToServer = ignore!(act.parse(&*msg, ctx));
         match msg {
-            ObserveToServer::Chat(chat) => act.inner.room.do_send(Chat::Observe(chat, act.inner.no)),
+            ObserveToServer::Chat(channel, chat) => {
+                if act.limit(LimitKind::Chat, ctx) {
+                    act.inner
+                        .room
+                        .do_send(Chat::Observe(channel, chat, act.inner.no, ctx.address()));
+                }
+            }
+            ObserveToServer::DeleteChat(id) => {
+                if act.limit(LimitKind::Command, ctx) {
+                    act.inner
+                        .room
+                        .do_send(Moderate(act.inner.no, act.inner.is_admin, ChatAction::Delete(id)));
+                }
+            }
         }
     }
+
+    fn error(code: &str, message: String) -> Option<Self::Sender> {
+        Some(ObserveToClient::Error {
+            code: code.to_owned(),
+            message,
+        })
+    }
 }
 
 impl Observe {
-    pub fn new(room: Addr<Room>, no: UserNo) -> Observe {
-        Observe { room, no }
+    pub fn new(room: Addr<Room>, no: UserNo, is_admin: bool) -> Observe {
+        Observe { room, no, is_admin }
     }
 }
@@ -1,16 +1,24 @@
 use crate::actor::hub::RemoveRoom;
 use crate::actor::session::Session;
-use crate::actor::user::{ChangeRating, GotGameState, GotRoomInfo, SendChat};
+use crate::actor::user::{
+    ChangeRating, DeleteChat, GotAdvice, GotError, GotGameState, GotInvalidRule, GotRoomInfo, GotUndoRequest, SendChat,
+};
 use crate::actor::{hub, Hub, List, Observe, User};
+use crate::chat::{now_millis, ChatConfig};
+use crate::db::chat::{delete_chat, save_chat, DeleteChatForm, SaveChatForm};
 use crate::db::game::{
     change_room_info, get_into_room, get_rule, leave_room, make_game, save_rule, save_state, ChangeRoomInfoForm,
     GetInRoomForm, GetRuleForm, LeaveRoomForm, MakeGameForm, SaveRuleForm, SaveStateForm,
 };
 use crate::dev::*;
 use actix::prelude::*;
-use mighty::prelude::{Command, Game, Rule, State};
+use actix_web::error::BlockingError;
+use actix_web::web;
+use mighty::error::{Error as GameError, Result as GameResult};
+use mighty::prelude::{Advice, Command, Game, Rule, State};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
 
 /// Information of game
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +40,17 @@ pub struct Room {
     list: HashSet<Addr<Session<List>>>,
     hub: Addr<Hub>,
     pool: Pool,
+    chat: ChatConfig,
+    chat_no: u64,
+    history: VecDeque<ChatMessage>,
+    muted: HashSet<UserNo>,
+    spectate_buffer: VecDeque<State>,
+    // delayed states for `SpectateMode::DelaySeconds` which are not sent yet
+    spectate_timers: Vec<SpawnHandle>,
+    // user who requested to take back the last command, and users who accepted it
+    undo_request: Option<(UserNo, HashSet<UserNo>)>,
+    // users whose advice is being estimated on the thread pool
+    pending_advice: HashSet<UserNo>,
 }
 
 impl Actor for Room {
@@ -73,6 +92,9 @@ impl Handler<RoomJoin> for Room {
                 let _ = get_into_room(&GetInRoomForm { room_id: self.info.id }, self.pool.clone());
             }
             RoomJoin::Observe(addr) => {
+                for chat in self.history.iter().filter(|chat| chat.channel != ChatChannel::Table) {
+                    addr.do_send(ObserveToClient::Chat(chat.clone()));
+                }
                 self.observe.insert(addr);
                 self.info.observer_cnt += 1;
                 self.spread_info();
@@ -162,6 +184,7 @@ impl Handler<ChangeName> for Room {
 
 /// Changes the rule of the room.
 /// It won't be changed if the user is not head.
+/// Invalid rule is rejected with every violated constraint.
 #[derive(Debug, Clone, Message)]
 #[rtype(result = "()")]
 pub struct ChangeRule(pub UserNo, pub Rule);
@@ -173,6 +196,12 @@ impl Handler<ChangeRule> for Room {
         if msg.0 != self.info.head || self.info.is_game {
             return;
         }
+        if let Err(errors) = msg.1.validate() {
+            if let Some(addr) = self.user_addr.get(&msg.0) {
+                addr.do_send(GotInvalidRule(errors));
+            }
+            return;
+        }
         self.info.rule = RuleHash::generate(&msg.1);
         let _ = save_rule(&SaveRuleForm { rule: msg.1.clone() }, self.pool.clone());
 
@@ -186,6 +215,43 @@ impl Handler<ChangeRule> for Room {
     }
 }
 
+/// Changes the spectating mode of the room.
+/// It won't be changed if the user is not head or the game is going on.
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct ChangeSpectateMode(pub UserNo, pub SpectateMode);
+
+impl Handler<ChangeSpectateMode> for Room {
+    type Result = ();
+
+    fn handle(&mut self, msg: ChangeSpectateMode, ctx: &mut Self::Context) -> Self::Result {
+        if msg.0 != self.info.head || self.info.is_game {
+            return;
+        }
+        self.clear_spectate(ctx);
+        self.info.spectate = msg.1;
+        self.spread_info();
+    }
+}
+
+/// Allows or disallows taking back the last command.
+/// It won't be changed if the user is not head or the game is going on, and it is always disallowed in rank room.
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct ChangeAllowUndo(pub UserNo, pub bool);
+
+impl Handler<ChangeAllowUndo> for Room {
+    type Result = ();
+
+    fn handle(&mut self, msg: ChangeAllowUndo, _: &mut Self::Context) -> Self::Result {
+        if msg.0 != self.info.head || self.info.is_game {
+            return;
+        }
+        self.info.allow_undo = msg.1 && !self.info.is_rank;
+        self.spread_info();
+    }
+}
+
 /// Starts the game.
 /// It won't be changed if the user is not head.
 #[derive(Debug, Clone, Message)]
@@ -195,26 +261,27 @@ pub struct StartGame(pub UserNo);
 impl Handler<StartGame> for Room {
     type Result = ();
 
-    fn handle(&mut self, msg: StartGame, _: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, msg: StartGame, ctx: &mut Self::Context) -> Self::Result {
         if msg.0 != self.info.head || self.info.is_game {
             return;
         }
         let id = GameId::generate_random();
-        let rule = get_rule(
+        let rule = ignore!(get_rule(
             &GetRuleForm {
                 rule_hash: self.info.rule,
             },
             self.pool.clone(),
-        )
-        .unwrap();
+        ));
         self.game = Some(GameInfo {
             id,
             no: 0,
             game: Game::new(rule.clone()),
         });
         self.info.is_game = true;
+        self.clear_spectate(ctx);
+        self.undo_request = None;
         self.spread_info();
-        self.spread_game();
+        self.spread_game(ctx);
         let form = MakeGameForm {
             game_id: id,
             room_id: self.info.uid,
@@ -235,7 +302,7 @@ pub struct Go(pub UserNo, pub Command);
 impl Handler<Go> for Room {
     type Result = ();
 
-    fn handle(&mut self, msg: Go, _: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, msg: Go, ctx: &mut Self::Context) -> Self::Result {
         if !self.info.is_game {
             return;
         }
@@ -249,37 +316,25 @@ impl Handler<Go> for Room {
         }
 
         if user_id == self.info.user.len() {
+            self.send_error(msg.0, GameError::NotPlayer);
             return;
         }
-        let finished = ignore!(self.next(user_id, msg.1));
-        let game = self.game.as_ref().unwrap();
-        let _ = save_state(
-            &SaveStateForm {
-                game_id: game.id,
-                room_id: self.info.uid,
-                number: game.no,
-                state: game.game.get_state(),
-            },
-            self.pool.clone(),
-        );
+        let finished = match self.next(user_id, msg.1, ctx) {
+            Ok(finished) => finished,
+            Err(e) => {
+                self.send_error(msg.0, e);
+                return;
+            }
+        };
+        // the last command is changed, so the request is not valid anymore
+        self.close_undo_request();
+        self.save_game_state();
 
         if finished {
             if self.info.is_rank {
                 if let Some(game) = &mut self.game {
-                    if let State::GameEnded {
-                        winner,
-                        president,
-                        score,
-                        ..
-                    } = game.game.state
-                    {
-                        for (i, &userno) in self.info.user.iter().enumerate() {
-                            let score = if (1 << i) & winner > 0 {
-                                score as i32
-                            } else {
-                                -(score as i32)
-                            };
-                            let score = if i == president { 2 * score } else { score };
+                    if let State::GameEnded { scores, .. } = &game.game.state {
+                        for (&userno, &score) in self.info.user.iter().zip(scores.iter()) {
                             self.user_addr
                                 .get(&userno)
                                 .unwrap()
@@ -295,37 +350,306 @@ impl Handler<Go> for Room {
     }
 }
 
-/// Returns the information of this room.
+/// Request to take back the last command, which is allowed only before the next user plays
+///
+/// - `Request`: The user requests to take back the user's last command
+/// - `Answer`: The user accepts or rejects the request
+///
+/// The command is taken back when every other user or the head accepts it.
+/// The request is closed by any rejection, or when anyone plays next.
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub enum Undo {
+    Request(UserNo),
+    Answer(UserNo, bool),
+}
+
+impl Handler<Undo> for Room {
+    type Result = ();
+
+    fn handle(&mut self, msg: Undo, ctx: &mut Self::Context) -> Self::Result {
+        if !self.info.is_game {
+            return;
+        }
+        match msg {
+            Undo::Request(user_no) => {
+                if !self.info.allow_undo || self.info.is_rank {
+                    self.send_reject(
+                        user_no,
+                        "undo_disabled",
+                        "taking back is not allowed in this room".to_owned(),
+                    );
+                    return;
+                }
+                let user_id = match self.info.user.iter().position(|x| *x == user_no) {
+                    Some(user_id) => user_id,
+                    None => {
+                        self.send_error(user_no, GameError::NotPlayer);
+                        return;
+                    }
+                };
+                if !self.game.as_ref().map_or(false, |g| g.game.can_undo(user_id)) {
+                    self.send_error(user_no, GameError::NoUndo);
+                    return;
+                }
+                self.undo_request = Some((user_no, HashSet::new()));
+                for (_, i) in self.user_addr.iter() {
+                    i.do_send(GotUndoRequest(Some(user_no)));
+                }
+            }
+            Undo::Answer(user_no, accept) => {
+                if !self.info.user.contains(&user_no) {
+                    return;
+                }
+                let (requester, accepted) = match &mut self.undo_request {
+                    Some((requester, accepted)) if *requester != user_no => (*requester, accepted),
+                    _ => return,
+                };
+                if !accept {
+                    self.close_undo_request();
+                    return;
+                }
+                accepted.insert(user_no);
+                let head = self.info.head;
+                if !accepted.contains(&head) && !self.info.user.iter().all(|u| *u == requester || accepted.contains(u))
+                {
+                    return;
+                }
+                self.close_undo_request();
+                let user_id = self.info.user.iter().position(|x| *x == requester).unwrap();
+                if let Err(e) = self.game.as_mut().unwrap().game.undo(user_id) {
+                    self.send_error(requester, e);
+                    return;
+                }
+                self.spread_game(ctx);
+                self.save_game_state();
+            }
+        }
+    }
+}
+
+/// Request for the estimated results of the pledges which the user can make in the election
+///
+/// Sampled games are played on the thread pool, and the result is sent only to the user.
+/// It is a hint for practice, so it is rejected in rank room.
+/// Each user can have only one pending request, so that one user can't fill the thread pool.
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct Advise(pub UserNo);
+
+impl Handler<Advise> for Room {
+    type Result = ();
+
+    fn handle(&mut self, msg: Advise, ctx: &mut Self::Context) -> Self::Result {
+        if !self.info.is_game {
+            return;
+        }
+        let user_no = msg.0;
+        if self.info.is_rank {
+            self.send_reject(
+                user_no,
+                "advice_disabled",
+                "advice is not allowed in rank room".to_owned(),
+            );
+            return;
+        }
+        let user_id = match self.info.user.iter().position(|x| *x == user_no) {
+            Some(user_id) => user_id,
+            None => {
+                self.send_error(user_no, GameError::NotPlayer);
+                return;
+            }
+        };
+        if !self.pending_advice.insert(user_no) {
+            self.send_reject(
+                user_no,
+                "advice_pending",
+                "previous advice is not finished yet".to_owned(),
+            );
+            return;
+        }
+        let game = &self.game.as_ref().unwrap().game;
+        let (rule, state) = (game.rule.clone(), game.state.clone());
+        web::block(move || Advice::estimate(&rule, &state, user_id, ADVICE_SAMPLES))
+            .into_actor(self)
+            .then(move |res, act, _| {
+                act.pending_advice.remove(&user_no);
+                match res {
+                    Ok(advice) => {
+                        if let Some(addr) = act.user_addr.get(&user_no) {
+                            addr.do_send(GotAdvice(advice));
+                        }
+                    }
+                    Err(BlockingError::Error(e)) => act.send_error(user_no, e),
+                    Err(BlockingError::Canceled) => {}
+                }
+
+                fut::ready(())
+            })
+            .spawn(ctx);
+    }
+}
+
+/// Sends chat to the channel of the room.
+/// The chat is filtered, saved and sent to whom can see the channel.
+/// It is rejected if it is invalid, the channel is not allowed, or the user is muted.
 #[derive(Debug, Clone, Message)]
 #[rtype(result = "()")]
 pub enum Chat {
-    User(String, UserNo),
-    Observe(String, UserNo),
+    User(ChatChannel, String, UserNo),
+    Observe(ChatChannel, String, UserNo, Addr<Session<Observe>>),
 }
 
 impl Handler<Chat> for Room {
     type Result = ();
 
     fn handle(&mut self, msg: Chat, _: &mut Self::Context) -> Self::Result {
-        match msg {
-            Chat::User(chat, no) => {
-                for (_, i) in self.user_addr.iter() {
-                    i.do_send(SendChat(chat.clone(), no));
+        let (channel, content, user_no) = match &msg {
+            Chat::User(channel, content, no) => (*channel, content, *no),
+            Chat::Observe(channel, content, no, _) => (*channel, content, *no),
+        };
+
+        let checked = if self.muted.contains(&user_no) {
+            Err(("muted", "you are muted in this room".to_owned()))
+        } else {
+            match (&msg, channel) {
+                (Chat::User(..), ChatChannel::Spectator) | (Chat::Observe(..), ChatChannel::Table) => {
+                    Err(("invalid_channel", "you can't chat to this channel".to_owned()))
+                }
+                (Chat::Observe(..), ChatChannel::Room) if self.info.is_game => Err((
+                    "spectator_in_game",
+                    "observers can't chat to the room during the game".to_owned(),
+                )),
+                _ => self.chat.check(content),
+            }
+        };
+        let content = match checked {
+            Ok(content) => content,
+            Err((code, message)) => {
+                match &msg {
+                    Chat::User(..) => self.send_reject(user_no, code, message),
+                    Chat::Observe(.., addr) => addr.do_send(ObserveToClient::Error {
+                        code: code.to_owned(),
+                        message,
+                    }),
                 }
+                return;
+            }
+        };
 
-                for i in self.observe.iter() {
-                    i.do_send(ObserveToClient::Chat(chat.clone(), no));
+        let chat = ChatMessage {
+            id: self.chat_no,
+            channel,
+            user: user_no,
+            content,
+            time: now_millis(),
+        };
+        self.chat_no += 1;
+
+        if channel != ChatChannel::Spectator {
+            for (_, i) in self.user_addr.iter() {
+                i.do_send(SendChat(chat.clone()));
+            }
+        }
+        if channel != ChatChannel::Table {
+            for i in self.observe.iter() {
+                i.do_send(ObserveToClient::Chat(chat.clone()));
+            }
+        }
+
+        let _ = save_chat(
+            &SaveChatForm {
+                room_id: self.info.uid,
+                game_id: self.game.as_ref().map(|g| g.id),
+                chat: chat.clone(),
+            },
+            self.pool.clone(),
+        );
+        self.history.push_back(chat);
+        while self.history.len() > self.chat.history_len {
+            self.history.pop_front();
+        }
+    }
+}
+
+/// Moderation of chat
+///
+/// - `Mute`: Rejects chats of the user in this room
+/// - `Unmute`: Allows chats of the user again
+/// - `Delete`: Deletes the chat with the id
+#[derive(Debug, Clone)]
+pub enum ChatAction {
+    Mute(UserNo),
+    Unmute(UserNo),
+    Delete(u64),
+}
+
+/// Moderates the chat with the user and whether the user is admin.
+/// Muting is allowed to head and admin, and deleting is allowed only to admin.
+/// Otherwise it will do nothing.
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct Moderate(pub UserNo, pub bool, pub ChatAction);
+
+impl Handler<Moderate> for Room {
+    type Result = ();
+
+    fn handle(&mut self, msg: Moderate, _: &mut Self::Context) -> Self::Result {
+        let Moderate(user_no, is_admin, action) = msg;
+        match action {
+            ChatAction::Mute(target) => {
+                if user_no == self.info.head || is_admin {
+                    self.muted.insert(target);
                 }
             }
-            Chat::Observe(chat, no) => {
+            ChatAction::Unmute(target) => {
+                if user_no == self.info.head || is_admin {
+                    self.muted.remove(&target);
+                }
+            }
+            ChatAction::Delete(id) => {
+                if !is_admin {
+                    return;
+                }
+                self.history.retain(|chat| chat.id != id);
+                for (_, i) in self.user_addr.iter() {
+                    i.do_send(DeleteChat(id));
+                }
                 for i in self.observe.iter() {
-                    i.do_send(ObserveToClient::Chat(chat.clone(), no));
+                    i.do_send(ObserveToClient::DeleteChat(id));
                 }
+                let _ = delete_chat(
+                    &DeleteChatForm {
+                        room_id: self.info.uid,
+                        no: id,
+                    },
+                    self.pool.clone(),
+                );
             }
         }
     }
 }
 
+/// Returns recent chats which can be seen by room users.
+/// Chats in `Spectator` channel are excluded.
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "Vec<ChatMessage>")]
+pub struct GetChatHistory;
+
+impl Handler<GetChatHistory> for Room {
+    type Result = MessageResult<GetChatHistory>;
+
+    fn handle(&mut self, _: GetChatHistory, _: &mut Self::Context) -> Self::Result {
+        MessageResult(
+            self.history
+                .iter()
+                .filter(|chat| chat.channel != ChatChannel::Spectator)
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
 /// Returns the information of this room.
 #[derive(Debug, Clone, Message)]
 #[rtype(result = "RoomInfo")]
@@ -340,7 +664,7 @@ impl Handler<GetInfo> for Room {
 }
 
 impl Room {
-    pub fn new(info: RoomInfo, server: Addr<hub::Hub>, pool: Pool) -> Room {
+    pub fn new(info: RoomInfo, server: Addr<hub::Hub>, pool: Pool, chat: ChatConfig) -> Room {
         Room {
             info,
             game: None,
@@ -349,6 +673,14 @@ impl Room {
             list: HashSet::new(),
             hub: server,
             pool,
+            chat,
+            chat_no: 0,
+            history: VecDeque::new(),
+            muted: HashSet::new(),
+            spectate_buffer: VecDeque::new(),
+            spectate_timers: Vec::new(),
+            undo_request: None,
+            pending_advice: HashSet::new(),
         }
     }
 
@@ -366,13 +698,45 @@ impl Room {
         }
     }
 
-    fn next(&mut self, user_id: usize, cmd: Command) -> Result<bool> {
+    fn next(&mut self, user_id: usize, cmd: Command, ctx: &mut Context<Self>) -> GameResult<bool> {
         if let Some(game) = &mut self.game {
             let res = game.game.next(user_id, cmd)?;
-            self.spread_game();
+            self.spread_game(ctx);
             Ok(res)
         } else {
-            bail!("game not started")
+            Err(GameError::Internal("game not started"))
+        }
+    }
+
+    fn save_game_state(&self) {
+        if let Some(game) = &self.game {
+            let _ = save_state(
+                &SaveStateForm {
+                    game_id: game.id,
+                    room_id: self.info.uid,
+                    number: game.no,
+                    state: game.game.get_state(),
+                },
+                self.pool.clone(),
+            );
+        }
+    }
+
+    fn close_undo_request(&mut self) {
+        if self.undo_request.take().is_some() {
+            for (_, i) in self.user_addr.iter() {
+                i.do_send(GotUndoRequest(None));
+            }
+        }
+    }
+
+    fn send_error(&self, user_no: UserNo, e: GameError) {
+        self.send_reject(user_no, e.code(), e.to_string());
+    }
+
+    fn send_reject(&self, user_no: UserNo, code: &str, message: String) {
+        if let Some(addr) = self.user_addr.get(&user_no) {
+            addr.do_send(GotError(code.to_owned(), message));
         }
     }
 
@@ -393,12 +757,47 @@ impl Room {
     }
 
     // assert: game is not `None`
-    fn spread_game(&self) {
-        let state = self.game.as_ref().unwrap().game.get_state();
+    fn spread_game(&mut self, ctx: &mut Context<Self>) {
+        let game = &self.game.as_ref().unwrap().game;
+        let state = game.get_state();
+        let finished = game.is_finished();
         for (_, i) in self.user_addr.iter() {
             i.do_send(GotGameState(state.clone()));
         }
 
+        match self.info.spectate {
+            SpectateMode::Live => self.spread_observe(state),
+            SpectateMode::HideHands => self.spread_observe(state.hide_hands(&game.rule)),
+            SpectateMode::DelaySeconds(secs) => {
+                let handle = ctx.run_later(Duration::from_secs(secs as u64), move |act, _| {
+                    act.spread_observe(state);
+                });
+                self.spectate_timers.push(handle);
+            }
+            SpectateMode::DelayMoves(_) | SpectateMode::AfterGame => {
+                let delay = match self.info.spectate {
+                    SpectateMode::DelayMoves(moves) => moves as usize,
+                    _ => usize::MAX,
+                };
+                self.spectate_buffer.push_back(state);
+                // every state is sent after the game ends
+                while self.spectate_buffer.len() > delay || (finished && !self.spectate_buffer.is_empty()) {
+                    let state = self.spectate_buffer.pop_front().unwrap();
+                    self.spread_observe(state);
+                }
+            }
+        }
+    }
+
+    /// Drops the states of the last game which are not sent to observers yet.
+    fn clear_spectate(&mut self, ctx: &mut Context<Self>) {
+        self.spectate_buffer.clear();
+        for handle in self.spectate_timers.drain(..) {
+            ctx.cancel_future(handle);
+        }
+    }
+
+    fn spread_observe(&self, state: State) {
         for i in self.observe.iter() {
             i.do_send(ObserveToClient::Game(state.clone()));
         }
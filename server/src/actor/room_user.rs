@@ -2,6 +2,7 @@ use crate::actor::session::{Session, SessionTrait};
 use crate::actor::user::{UserCommand, UserConnect, UserDisconnect};
 use crate::actor::User;
 use crate::dev::*;
+use crate::rate_limit::LimitKind;
 use actix::prelude::*;
 use actix_web_actors::ws::WebsocketContext;
 
@@ -21,10 +22,25 @@ impl SessionTrait for RoomUser {
         act.inner.user.do_send(UserDisconnect::Room(ctx.address()));
     }
 
-    fn receive(act: &mut Session<Self>, msg: String, _: &mut WebsocketContext<Session<Self>>) {
-        let msg: RoomUserToServer = serde_json::from_str(&*msg).unwrap();
+    fn receive(act: &mut Session<Self>, msg: String, ctx: &mut WebsocketContext<Session<Self>>) {
+        let msg: RoomUserToServer = ignore!(act.parse(&*msg, ctx));
+        let kind = match msg {
+            RoomUserToServer::Chat(_) => LimitKind::Chat,
+            RoomUserToServer::RequestAdvice => LimitKind::Advice,
+            _ => LimitKind::Command,
+        };
+        if !act.limit(kind, ctx) {
+            return;
+        }
         act.inner.user.do_send(UserCommand(msg));
     }
+
+    fn error(code: &str, message: String) -> Option<Self::Sender> {
+        Some(RoomUserToClient::Error {
+            code: code.to_owned(),
+            message,
+        })
+    }
 }
 
 impl RoomUser {
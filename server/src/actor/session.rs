@@ -1,8 +1,12 @@
 use crate::dev::*;
+use crate::rate_limit::{Bucket, LimitKind, RateLimit};
 use actix::prelude::*;
 use actix_web_actors::ws;
-use actix_web_actors::ws::WebsocketContext;
+use actix_web_actors::ws::{CloseCode, CloseReason, WebsocketContext};
+use mighty::error::Error as GameError;
+use serde::de::DeserializeOwned;
 use serde::Serialize;
+use std::collections::HashMap;
 use std::time::SystemTime;
 
 pub trait SessionTrait: Sized + Unpin + 'static {
@@ -18,8 +22,14 @@ pub trait SessionTrait: Sized + Unpin + 'static {
 
     fn receive(act: &mut Session<Self>, msg: String, ctx: &mut WebsocketContext<Session<Self>>);
 
-    fn make(self) -> Session<Self> {
-        Session::new(self)
+    /// Message to reply when the message from client is rejected.
+    /// If `None`, the client is not notified.
+    fn error(_: &str, _: String) -> Option<Self::Sender> {
+        None
+    }
+
+    fn make(self, rate_limit: RateLimit) -> Session<Self> {
+        Session::new(self, rate_limit)
     }
 }
 
@@ -30,6 +40,9 @@ where
 {
     pub inner: T,
     hb: SystemTime,
+    malformed: u32,
+    rate_limit: RateLimit,
+    buckets: HashMap<LimitKind, Bucket>,
 }
 
 impl<T> Actor for Session<T>
@@ -94,10 +107,55 @@ impl<T> Session<T>
 where
     T: SessionTrait,
 {
-    pub fn new(inner: T) -> Session<T> {
+    pub fn new(inner: T, rate_limit: RateLimit) -> Session<T> {
         Session {
             inner,
             hb: SystemTime::now(),
+            malformed: 0,
+            rate_limit,
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Parses the message from client.
+    ///
+    /// Malformed messages are replied with `SessionTrait::error` and counted.
+    /// When the count reaches `MALFORMED_MESSAGE_LIMIT`, the connection is closed.
+    pub fn parse<M: DeserializeOwned>(
+        &mut self,
+        msg: &str,
+        ctx: &mut WebsocketContext<Session<T>>,
+    ) -> Result<M, GameError> {
+        serde_json::from_str(msg).map_err(|e| {
+            let e = GameError::from(e);
+            self.malformed += 1;
+            if let Some(reply) = T::error(e.code(), e.to_string()) {
+                T::handle(self, reply, ctx);
+            }
+            if self.malformed >= MALFORMED_MESSAGE_LIMIT {
+                ctx.close(Some(CloseReason::from((
+                    CloseCode::Policy,
+                    "too many malformed messages",
+                ))));
+                ctx.stop();
+            }
+            e
+        })
+    }
+
+    /// Takes a token from the bucket of `kind`.
+    ///
+    /// If the client sends messages too fast, it is replied with `SessionTrait::error`
+    /// and returns `false`. Then the message should be dropped.
+    pub fn limit(&mut self, kind: LimitKind, ctx: &mut WebsocketContext<Session<T>>) -> bool {
+        let limit = self.rate_limit.get(kind);
+        if self.buckets.entry(kind).or_insert_with(|| Bucket::new(limit)).take() {
+            true
+        } else {
+            if let Some(reply) = T::error("rate_limited", "you are sending messages too fast".to_owned()) {
+                T::handle(self, reply, ctx);
+            }
+            false
         }
     }
 }
@@ -1,12 +1,16 @@
 use crate::actor::hub::GetRoom;
-use crate::actor::room::{ChangeName, ChangeRule, Chat, Go, RoomJoin, RoomLeave, StartGame};
+use crate::actor::room::{
+    Advise, ChangeAllowUndo, ChangeName, ChangeRule, ChangeSpectateMode, Chat, ChatAction, GetChatHistory, Go,
+    Moderate, RoomJoin, RoomLeave, StartGame, Undo,
+};
 use crate::actor::session::Session;
 use crate::actor::{Hub, Main, Room, RoomUser};
 use crate::db::game::{change_rating, ChangeRatingForm};
 use crate::dev::*;
 use actix::clock::Duration;
 use actix::prelude::*;
-use mighty::prelude::State;
+use mighty::error::RuleError;
+use mighty::prelude::{Advice, State};
 use std::collections::HashSet;
 use std::time::SystemTime;
 
@@ -30,6 +34,7 @@ pub struct User {
     disconn: u8,
     last_update: SystemTime,
     room: Option<JoinedRoom>,
+    main: HashSet<Addr<Session<Main>>>,
     subscribers: HashSet<Addr<Session<Main>>>,
     hub: Addr<Hub>,
     pool: Pool,
@@ -47,23 +52,39 @@ impl Actor for User {
 pub enum UserConnect {
     Room(Addr<Session<RoomUser>>),
     Subscribe(Addr<Session<Main>>),
-    Main,
+    Main(Addr<Session<Main>>),
 }
 
 impl Handler<UserConnect> for User {
     type Result = Result<()>;
 
-    fn handle(&mut self, msg: UserConnect, _: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, msg: UserConnect, ctx: &mut Self::Context) -> Self::Result {
         match msg {
             UserConnect::Room(addr) => {
                 ensure!(self.room.is_some(), StatusCode::BAD_REQUEST, "no joined room");
-                self.room.as_mut().unwrap().group.insert(addr);
+                let room = self.room.as_mut().unwrap();
+                room.group.insert(addr.clone());
+                let room_addr = room.addr.clone();
+                room_addr
+                    .send(GetChatHistory)
+                    .into_actor(self)
+                    .then(move |res, _, _| {
+                        if let Ok(history) = res {
+                            for chat in history {
+                                addr.do_send(RoomUserToClient::Chat(chat));
+                            }
+                        }
+
+                        fut::ready(())
+                    })
+                    .wait(ctx);
             }
             UserConnect::Subscribe(addr) => {
                 addr.do_send(MainToClient::UserStatus(self.info.no, self.status));
                 self.subscribers.insert(addr);
             }
-            UserConnect::Main => {
+            UserConnect::Main(addr) => {
+                self.main.insert(addr);
                 self.conn += 1;
                 self.update_status();
             }
@@ -78,7 +99,7 @@ impl Handler<UserConnect> for User {
 pub enum UserDisconnect {
     Room(Addr<Session<RoomUser>>),
     Unsubscribe(Addr<Session<Main>>),
-    Main,
+    Main(Addr<Session<Main>>),
 }
 
 impl Handler<UserDisconnect> for User {
@@ -102,7 +123,8 @@ impl Handler<UserDisconnect> for User {
             UserDisconnect::Unsubscribe(addr) => {
                 self.subscribers.remove(&addr);
             }
-            UserDisconnect::Main => {
+            UserDisconnect::Main(addr) => {
+                self.main.remove(&addr);
                 self.conn -= 1;
                 self.disconn += 1;
                 self.update_status();
@@ -201,11 +223,38 @@ impl Handler<UserCommand> for User {
             RoomUserToServer::ChangeRule(rule) => {
                 room.addr.do_send(ChangeRule(user_no, rule));
             }
+            RoomUserToServer::ChangeSpectateMode(mode) => {
+                room.addr.do_send(ChangeSpectateMode(user_no, mode));
+            }
+            RoomUserToServer::ChangeAllowUndo(allow) => {
+                room.addr.do_send(ChangeAllowUndo(user_no, allow));
+            }
             RoomUserToServer::Command(cmd) => {
                 room.addr.do_send(Go(user_no, cmd));
             }
-            RoomUserToServer::Chat(chat) => {
-                room.addr.do_send(Chat::User(chat, self.info.no));
+            RoomUserToServer::RequestUndo => {
+                room.addr.do_send(Undo::Request(user_no));
+            }
+            RoomUserToServer::AnswerUndo(accept) => {
+                room.addr.do_send(Undo::Answer(user_no, accept));
+            }
+            RoomUserToServer::RequestAdvice => {
+                room.addr.do_send(Advise(user_no));
+            }
+            RoomUserToServer::Chat(channel, chat) => {
+                room.addr.do_send(Chat::User(channel, chat, user_no));
+            }
+            RoomUserToServer::Mute(target) => {
+                room.addr
+                    .do_send(Moderate(user_no, self.info.is_admin, ChatAction::Mute(target)));
+            }
+            RoomUserToServer::Unmute(target) => {
+                room.addr
+                    .do_send(Moderate(user_no, self.info.is_admin, ChatAction::Unmute(target)));
+            }
+            RoomUserToServer::DeleteChat(id) => {
+                room.addr
+                    .do_send(Moderate(user_no, self.info.is_admin, ChatAction::Delete(id)));
             }
         }
     }
@@ -249,6 +298,82 @@ impl Handler<GotGameState> for User {
     }
 }
 
+/// Passing error code & message of the rejected command to user
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct GotError(pub String, pub String);
+
+impl Handler<GotError> for User {
+    type Result = ();
+
+    fn handle(&mut self, msg: GotError, _: &mut Self::Context) -> Self::Result {
+        if self.room.is_none() {
+            return;
+        }
+        for i in self.room.as_ref().unwrap().group.iter() {
+            i.do_send(RoomUserToClient::Error {
+                code: msg.0.clone(),
+                message: msg.1.clone(),
+            });
+        }
+    }
+}
+
+/// Passing every violated constraint of the rejected rule to user
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct GotInvalidRule(pub Vec<RuleError>);
+
+impl Handler<GotInvalidRule> for User {
+    type Result = ();
+
+    fn handle(&mut self, msg: GotInvalidRule, _: &mut Self::Context) -> Self::Result {
+        if self.room.is_none() {
+            return;
+        }
+        let errors = msg.0.iter().map(RuleViolation::from).collect::<Vec<_>>();
+        for i in self.room.as_ref().unwrap().group.iter() {
+            i.do_send(RoomUserToClient::InvalidRule(errors.clone()));
+        }
+    }
+}
+
+/// Passing the request to take back the last command to user
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct GotUndoRequest(pub Option<UserNo>);
+
+impl Handler<GotUndoRequest> for User {
+    type Result = ();
+
+    fn handle(&mut self, msg: GotUndoRequest, _: &mut Self::Context) -> Self::Result {
+        if self.room.is_none() {
+            return;
+        }
+        for i in self.room.as_ref().unwrap().group.iter() {
+            i.do_send(RoomUserToClient::UndoRequest(msg.0));
+        }
+    }
+}
+
+/// Passing the estimated results of the pledges to user
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct GotAdvice(pub Vec<Advice>);
+
+impl Handler<GotAdvice> for User {
+    type Result = ();
+
+    fn handle(&mut self, msg: GotAdvice, _: &mut Self::Context) -> Self::Result {
+        if self.room.is_none() {
+            return;
+        }
+        for i in self.room.as_ref().unwrap().group.iter() {
+            i.do_send(RoomUserToClient::Advice(msg.0.clone()));
+        }
+    }
+}
+
 /// Update for absent
 #[derive(Debug, Clone, Message)]
 #[rtype(result = "()")]
@@ -294,9 +419,10 @@ impl Handler<ChangeRating> for User {
     }
 }
 
+/// Passing chat to user
 #[derive(Debug, Clone, Message)]
 #[rtype(result = "()")]
-pub struct SendChat(pub String, pub UserNo);
+pub struct SendChat(pub ChatMessage);
 
 impl Handler<SendChat> for User {
     type Result = ();
@@ -308,7 +434,42 @@ impl Handler<SendChat> for User {
 
         let room = self.room.as_mut().unwrap();
         for i in room.group.iter() {
-            i.do_send(RoomUserToClient::Chat(msg.0.clone(), msg.1));
+            i.do_send(RoomUserToClient::Chat(msg.0.clone()));
+        }
+    }
+}
+
+/// Passing id of deleted chat to user
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct DeleteChat(pub u64);
+
+impl Handler<DeleteChat> for User {
+    type Result = ();
+
+    fn handle(&mut self, msg: DeleteChat, _: &mut Self::Context) -> Self::Result {
+        if self.room.is_none() {
+            return;
+        }
+
+        for i in self.room.as_ref().unwrap().group.iter() {
+            i.do_send(RoomUserToClient::DeleteChat(msg.0));
+        }
+    }
+}
+
+/// Passing direct message to user
+/// It is sent to both of the sender and the receiver.
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct GotDirectMessage(pub DirectMessage);
+
+impl Handler<GotDirectMessage> for User {
+    type Result = ();
+
+    fn handle(&mut self, msg: GotDirectMessage, _: &mut Self::Context) -> Self::Result {
+        for i in self.main.iter() {
+            i.do_send(MainToClient::DirectMessage(msg.0.clone()));
         }
     }
 }
@@ -322,6 +483,7 @@ impl User {
             disconn: 0,
             last_update: SystemTime::now(),
             room: None,
+            main: HashSet::new(),
             subscribers: HashSet::new(),
             hub,
             pool,
@@ -1,6 +1,8 @@
 use crate::actor::{Hub, Mail};
+use crate::chat::ChatConfig;
 use crate::db;
 use crate::dev::*;
+use crate::rate_limit::{HttpLimiter, RateLimit};
 use actix::prelude::*;
 use actix_web::web;
 use handlebars::{Handlebars, RenderError};
@@ -29,6 +31,8 @@ use {
 /// 4. `hub`: Websocket main server address.
 /// 5. `pool`: Database pool.
 /// 6. `mail`: Mail sender.
+/// 7. `rate_limit`: Rate limit configuration for websocket sessions.
+/// 8. `limiter`: Rate limiters for http requests.
 pub struct AppState {
     #[cfg(not(feature = "watch-file"))]
     handlebars: Handlebars<'static>,
@@ -45,11 +49,20 @@ pub struct AppState {
     pub pool: Pool,
     pub mail: Addr<Mail>,
     pub secret: String,
+    pub rate_limit: RateLimit,
+    pub limiter: HttpLimiter,
 }
 
 impl AppState {
     #[cfg(not(feature = "watch-file"))]
-    pub fn new<P: AsRef<Path>>(path: P, config: PgConfig, mail: Addr<Mail>, secret: String) -> web::Data<AppState> {
+    pub fn new<P: AsRef<Path>>(
+        path: P,
+        config: PgConfig,
+        mail: Addr<Mail>,
+        secret: String,
+        rate_limit: RateLimit,
+        chat: ChatConfig,
+    ) -> web::Data<AppState> {
         let manager = PostgresConnectionManager::new(config, NoTls);
         let pool = Pool::new(manager).unwrap();
         db::init(pool.clone()).expect("db init failed");
@@ -57,15 +70,24 @@ impl AppState {
         web::Data::new(AppState {
             handlebars: make_handlebars(&path),
             resources: get_resources(&path),
-            hub: Hub::new(pool.clone()).start(),
+            hub: Hub::new(pool.clone(), chat).start(),
             pool,
             mail,
             secret,
+            limiter: HttpLimiter::new(&rate_limit),
+            rate_limit,
         })
     }
 
     #[cfg(feature = "watch-file")]
-    pub fn new<P: AsRef<Path>>(path: P, config: PgConfig, mail: Addr<Mail>, secret: String) -> web::Data<AppState> {
+    pub fn new<P: AsRef<Path>>(
+        path: P,
+        config: PgConfig,
+        mail: Addr<Mail>,
+        secret: String,
+        rate_limit: RateLimit,
+        chat: ChatConfig,
+    ) -> web::Data<AppState> {
         let path = path.as_ref();
         let (tx, rx) = channel();
         let mut watcher = raw_watcher(tx).unwrap();
@@ -80,10 +102,12 @@ impl AppState {
             handlebars: Mutex::new(make_handlebars(&path)),
             watcher,
             resources: Mutex::new(get_resources(&path)),
-            hub: Hub::new(pool.clone()).start(),
+            hub: Hub::new(pool.clone(), chat).start(),
             pool,
             mail,
             secret,
+            limiter: HttpLimiter::new(&rate_limit),
+            rate_limit,
         });
         let state_clone = state.clone();
         let path_clone = path.to_path_buf();
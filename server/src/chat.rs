@@ -0,0 +1,140 @@
+//! # Chat
+//!
+//! Configuration and filters for chats in room.
+//! Filter is applied before the chat is saved or sent, so banned words are never stored.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Filter to change the content of chat
+///
+/// Implement this to use other filter than `WordFilter`.
+pub trait ChatFilter: Debug + Send + Sync {
+    fn filter(&self, content: &str) -> String;
+}
+
+/// Filter which masks banned words with `*`
+///
+/// Words are matched case-insensitively, and only the matched characters are masked.
+#[derive(Debug, Clone, Default)]
+pub struct WordFilter {
+    words: Vec<Vec<char>>,
+}
+
+impl WordFilter {
+    pub fn new<S: AsRef<str>>(words: &[S]) -> WordFilter {
+        WordFilter {
+            words: words
+                .iter()
+                .map(|w| w.as_ref().trim().chars().collect::<Vec<_>>())
+                .filter(|w| !w.is_empty())
+                .collect(),
+        }
+    }
+}
+
+fn char_eq(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+impl ChatFilter for WordFilter {
+    fn filter(&self, content: &str) -> String {
+        let chars = content.chars().collect::<Vec<_>>();
+        let mut masked = vec![false; chars.len()];
+
+        for word in self.words.iter() {
+            if word.len() > chars.len() {
+                continue;
+            }
+            for i in 0..=(chars.len() - word.len()) {
+                if word.iter().zip(chars[i..].iter()).all(|(&a, &b)| char_eq(a, b)) {
+                    for m in masked[i..i + word.len()].iter_mut() {
+                        *m = true;
+                    }
+                }
+            }
+        }
+
+        chars
+            .into_iter()
+            .zip(masked)
+            .map(|(c, m)| if m { '*' } else { c })
+            .collect()
+    }
+}
+
+/// Chat configuration of room
+///
+/// - `max_len`: maximum count of characters in one chat
+/// - `history_len`: count of recent chats sent to who joins the room
+/// - `filter`: filter applied to every chat
+#[derive(Debug, Clone)]
+pub struct ChatConfig {
+    pub max_len: usize,
+    pub history_len: usize,
+    pub filter: Arc<dyn ChatFilter>,
+}
+
+impl Default for ChatConfig {
+    fn default() -> Self {
+        ChatConfig {
+            max_len: 300,
+            history_len: 50,
+            filter: Arc::new(WordFilter::default()),
+        }
+    }
+}
+
+impl ChatConfig {
+    /// Checks the length of content and applies the filter.
+    /// Returns the error code & message if the chat should be rejected.
+    pub fn check(&self, content: &str) -> Result<String, (&'static str, String)> {
+        let content = content.trim();
+        if content.is_empty() {
+            Err(("empty_chat", "chat is empty".to_owned()))
+        } else if content.chars().count() > self.max_len {
+            Err((
+                "chat_too_long",
+                format!("chat should be at most {} characters", self.max_len),
+            ))
+        } else {
+            Ok(self.filter.filter(content))
+        }
+    }
+}
+
+/// Current unix time in milliseconds
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn word_filter_test() {
+        let filter = WordFilter::new(&["bad", "나쁜", " "]);
+        assert_eq!(filter.filter("this is BaD!"), "this is ***!");
+        assert_eq!(filter.filter("badbad bd"), "****** bd");
+        assert_eq!(filter.filter("나쁜 말"), "** 말");
+        assert_eq!(filter.filter("ba"), "ba");
+        assert_eq!(filter.filter(""), "");
+    }
+
+    #[test]
+    fn check_test() {
+        let config = ChatConfig {
+            max_len: 3,
+            history_len: 0,
+            filter: Arc::new(WordFilter::new(&["b"])),
+        };
+        assert_eq!(config.check(" abc "), Ok("a*c".to_owned()));
+        assert_eq!(config.check("  ").unwrap_err().0, "empty_chat");
+        assert_eq!(config.check("abcd").unwrap_err().0, "chat_too_long");
+    }
+}
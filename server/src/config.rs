@@ -26,6 +26,21 @@
 //!   * `username`: username to mail server (defaults to `admin`)
 //!   * `password`: password to mail server (defaults to `admin`)
 //!   * `host`: host of mail server (defaults to `localhost:587`)
+//! - `rate_limit`: rate limit configuration; each has `burst` (maximum messages at once) and
+//!                 `per_minute` (messages refilled in a minute)
+//!   * `chat`: chat messages in room (defaults to `5`, `30`)
+//!   * `command`: other websocket messages (defaults to `20`, `240`)
+//!   * `room_list`: requests for room list (defaults to `3`, `20`)
+//!   * `advice`: requests for pledge advice, which play many games on the thread pool (defaults to `2`, `6`)
+//!   * `login`: login requests per ip (defaults to `5`, `10`)
+//!   * `pre_register`: pre-register requests per ip (defaults to `2`, `4`)
+//!   * `regenerate_token`: token regeneration requests per ip (defaults to `2`, `4`)
+//!   * `review`: game review requests per user (defaults to `2`, `6`)
+//!   * `trusted_proxies`: addresses of proxies whose `X-Forwarded-For` is trusted (defaults to empty)
+//! - `chat`: chat configuration
+//!   * `max_len`: maximum characters of one chat (defaults to `300`)
+//!   * `history_len`: count of recent chats sent on joining the room (defaults to `50`)
+//!   * `banned_words`: words masked with `*` in chats (defaults to empty)
 //!
 //! # Examples
 //!
@@ -56,6 +71,16 @@
 //! username = "admin"
 //! password = "secret"
 //! host = "0.0.0.0"
+//!
+//! [rate_limit]
+//! chat = { burst = 5, per_minute = 30 }
+//! login = { burst = 5, per_minute = 10 }
+//! trusted_proxies = ["127.0.0.1"]
+//!
+//! [chat]
+//! max_len = 300
+//! history_len = 50
+//! banned_words = ["badword"]
 //! ```
 //!
 //! ## Environment example
@@ -82,11 +107,19 @@
 //! MAIL__USERNAME="admin"
 //! MAIL__PASSWORD="secret"
 //! MAIL__HOST="0.0.0.0"
+//!
+//! RATE_LIMIT__CHAT__BURST="5"
+//! RATE_LIMIT__CHAT__PER_MINUTE="30"
+//!
+//! CHAT__MAX_LEN="300"
+//! CHAT__HISTORY_LEN="50"
 //! ```
 
 use crate::actor;
+use crate::chat::{ChatConfig, WordFilter};
 use crate::dev::*;
 use crate::path::{join, to_absolute_path};
+use crate::rate_limit::{Limit, RateLimit};
 use config::{Environment, File};
 use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod};
 use rand::distributions::Standard;
@@ -99,7 +132,9 @@ use slog_scope::{set_global_logger, GlobalLoggerGuard};
 use slog_term::{FullFormat, PlainDecorator, TermDecorator};
 use std::env;
 use std::fs::OpenOptions;
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 /// Using `deadpoool_postgres::Config` just for serde
 type DpConfig = deadpool_postgres::Config;
@@ -258,6 +293,59 @@ impl Builder {
             }
         });
 
+        let mut rate_limit_builder = RateLimitBuilder::default();
+
+        for (_, c) in self.builders.iter() {
+            if let Some(rate_limit) = &c.rate_limit {
+                rate_limit_builder.chat = rate_limit_builder.chat.or(rate_limit.chat);
+                rate_limit_builder.command = rate_limit_builder.command.or(rate_limit.command);
+                rate_limit_builder.room_list = rate_limit_builder.room_list.or(rate_limit.room_list);
+                rate_limit_builder.advice = rate_limit_builder.advice.or(rate_limit.advice);
+                rate_limit_builder.login = rate_limit_builder.login.or(rate_limit.login);
+                rate_limit_builder.pre_register = rate_limit_builder.pre_register.or(rate_limit.pre_register);
+                rate_limit_builder.regenerate_token =
+                    rate_limit_builder.regenerate_token.or(rate_limit.regenerate_token);
+                rate_limit_builder.review = rate_limit_builder.review.or(rate_limit.review);
+                rate_limit_builder.trusted_proxies = rate_limit_builder
+                    .trusted_proxies
+                    .or_else(|| rate_limit.trusted_proxies.clone());
+            }
+        }
+
+        let default_limit = RateLimit::default();
+        let rate_limit = RateLimit {
+            chat: rate_limit_builder.chat.unwrap_or(default_limit.chat),
+            command: rate_limit_builder.command.unwrap_or(default_limit.command),
+            room_list: rate_limit_builder.room_list.unwrap_or(default_limit.room_list),
+            advice: rate_limit_builder.advice.unwrap_or(default_limit.advice),
+            login: rate_limit_builder.login.unwrap_or(default_limit.login),
+            pre_register: rate_limit_builder.pre_register.unwrap_or(default_limit.pre_register),
+            regenerate_token: rate_limit_builder
+                .regenerate_token
+                .unwrap_or(default_limit.regenerate_token),
+            review: rate_limit_builder.review.unwrap_or(default_limit.review),
+            trusted_proxies: rate_limit_builder
+                .trusted_proxies
+                .unwrap_or(default_limit.trusted_proxies),
+        };
+
+        let mut chat_builder = ChatBuilder::default();
+
+        for (_, c) in self.builders.iter() {
+            if let Some(chat) = &c.chat {
+                chat_builder.max_len = chat_builder.max_len.or(chat.max_len);
+                chat_builder.history_len = chat_builder.history_len.or(chat.history_len);
+                chat_builder.banned_words = chat_builder.banned_words.or_else(|| chat.banned_words.clone());
+            }
+        }
+
+        let default_chat = ChatConfig::default();
+        let chat = Chat {
+            max_len: chat_builder.max_len.unwrap_or(default_chat.max_len),
+            history_len: chat_builder.history_len.unwrap_or(default_chat.history_len),
+            banned_words: chat_builder.banned_words.unwrap_or_default(),
+        };
+
         Config {
             postgres,
             host,
@@ -273,6 +361,8 @@ impl Builder {
                 password,
                 host: mail_host,
             },
+            rate_limit,
+            chat,
         }
     }
 }
@@ -290,6 +380,8 @@ struct ConfigBuilder {
     serve_path: Option<String>,
     secret: Option<String>,
     mail: Option<MailBuilder>,
+    rate_limit: Option<RateLimitBuilder>,
+    chat: Option<ChatBuilder>,
 }
 
 /// Https configuration builder
@@ -310,6 +402,28 @@ struct MailBuilder {
     host: Option<String>,
 }
 
+/// Rate limit configuration builder
+#[derive(Debug, Clone, Default, Deserialize)]
+struct RateLimitBuilder {
+    chat: Option<Limit>,
+    command: Option<Limit>,
+    room_list: Option<Limit>,
+    advice: Option<Limit>,
+    login: Option<Limit>,
+    pre_register: Option<Limit>,
+    regenerate_token: Option<Limit>,
+    review: Option<Limit>,
+    trusted_proxies: Option<Vec<IpAddr>>,
+}
+
+/// Chat configuration builder
+#[derive(Debug, Clone, Default, Deserialize)]
+struct ChatBuilder {
+    max_len: Option<usize>,
+    history_len: Option<usize>,
+    banned_words: Option<Vec<String>>,
+}
+
 impl ConfigBuilder {
     /// Generate `ConfigBuilder` from the given path.
     pub fn from_file(path: PathBuf) -> ConfigBuilder {
@@ -337,6 +451,8 @@ pub struct Config {
     pub serve_path: PathBuf,
     pub secret: Vec<u8>,
     pub mail: Mail,
+    pub rate_limit: RateLimit,
+    pub chat: Chat,
 }
 
 /// Https configuration struct
@@ -357,6 +473,14 @@ pub struct Mail {
     pub host: String,
 }
 
+/// Chat configuration struct
+#[derive(Clone)]
+pub struct Chat {
+    pub max_len: usize,
+    pub history_len: usize,
+    pub banned_words: Vec<String>,
+}
+
 impl Config {
     pub fn builder() -> Builder {
         Builder { builders: Vec::new() }
@@ -405,6 +529,15 @@ impl Config {
         )
     }
 
+    /// Function to get chat configuration
+    pub fn get_chat(&self) -> ChatConfig {
+        ChatConfig {
+            max_len: self.chat.max_len,
+            history_len: self.chat.history_len,
+            filter: Arc::new(WordFilter::new(&self.chat.banned_words)),
+        }
+    }
+
     /// Function to get ssl builder (assuming https is enabled)
     pub fn get_ssl_builder(&self) -> SslAcceptorBuilder {
         let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
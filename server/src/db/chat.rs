@@ -0,0 +1,45 @@
+use crate::dev::*;
+use postgres::types::Json;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, UNIX_EPOCH};
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct SaveChatForm {
+    pub room_id: RoomUid,
+    pub game_id: Option<GameId>,
+    pub chat: ChatMessage,
+}
+
+pub fn save_chat(form: &SaveChatForm, pool: Pool) -> Result<()> {
+    let mut client = pool.get()?;
+    let stmt = client.prepare(
+        "INSERT INTO chats (room_id, game_id, no, channel, user_no, content, time) VALUES ($1, $2, $3, $4, $5, $6, $7);",
+    )?;
+    let time = UNIX_EPOCH + Duration::from_millis(form.chat.time);
+    let _ = client.query(
+        &stmt,
+        &[
+            &form.room_id.to_string(),
+            &form.game_id.map(|id| id.to_string()),
+            &(form.chat.id as i64),
+            &Json(&form.chat.channel),
+            &form.chat.user.0,
+            &form.chat.content,
+            &time,
+        ],
+    )?;
+    Ok(())
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct DeleteChatForm {
+    pub room_id: RoomUid,
+    pub no: u64,
+}
+
+pub fn delete_chat(form: &DeleteChatForm, pool: Pool) -> Result<()> {
+    let mut client = pool.get()?;
+    let stmt = client.prepare("UPDATE chats SET deleted=TRUE WHERE room_id=$1 AND no=$2;")?;
+    let _ = client.query(&stmt, &[&form.room_id.to_string(), &(form.no as i64)])?;
+    Ok(())
+}
@@ -69,24 +69,27 @@ pub struct MakeGameForm {
     pub rule: Rule,
 }
 
+/// Saves the game with the hash of its rule, and the rule itself to `rules`
 pub fn make_game(form: &MakeGameForm, pool: Pool) -> Result<()> {
     let mut client = pool.get()?;
-    let stmt = client.prepare("SELECT 1 is_gaming FROM curr_rooms WHERE uid=$1")?;
-    let res = client.query(&stmt, &[&form.room_id.to_string()])?;
-    let is_gaming: bool = res[0].get(0);
-    ensure!(!is_gaming, "game is already going on the room");
-    let mut client = pool.get()?;
+    let rule_hash = RuleHash::generate(&form.rule).to_string();
+    let stmt =
+        client.prepare("INSERT INTO rules (rule_hash, rule) VALUES ($1, $2) ON CONFLICT (rule_hash) DO NOTHING;")?;
+    let _ = client.query(&stmt, &[&rule_hash, &Json(&form.rule)])?;
+
     let stmt = client
         .prepare("INSERT INTO games (id, room_id, room_name, users, is_rank, rule) VALUES ($1, $2, $3, $4, $5, $6);")?;
+    // `u32` is taken as `OID` by postgres, and the columns are `INTEGER`
+    let users = form.users.iter().map(|u| *u as i32).collect::<Vec<_>>();
     let _ = client.query(
         &stmt,
         &[
             &form.game_id.to_string(),
             &form.room_id.to_string(),
             &form.room_name,
-            &form.users,
+            &users,
             &form.is_rank,
-            &Json(&form.rule),
+            &rule_hash,
         ],
     )?;
     Ok(())
@@ -102,19 +105,41 @@ pub struct SaveStateForm {
 
 pub fn save_state(form: &SaveStateForm, pool: Pool) -> Result<()> {
     let mut client = pool.get()?;
-    let stmt = client.prepare("INSERT INTO record (game_id, room_id, number, state) VALUES ($1, $2, $3, $4);")?;
+    let stmt = client.prepare("INSERT INTO records (game_id, room_id, number, state) VALUES ($1, $2, $3, $4);")?;
     let _ = client.query(
         &stmt,
         &[
             &form.game_id.to_string(),
             &form.room_id.to_string(),
-            &form.number,
+            &(form.number as i32),
             &Json(&form.state),
         ],
     )?;
     Ok(())
 }
 
+#[derive(Deserialize, Serialize, Clone)]
+pub struct GetGameResultForm {
+    pub game_id: GameId,
+}
+
+/// Rule of the game and its last state
+pub fn get_game_result(form: &GetGameResultForm, pool: Pool) -> Result<(Rule, State)> {
+    let mut client = pool.get()?;
+    let stmt =
+        client.prepare("SELECT rules.rule FROM games JOIN rules ON rules.rule_hash = games.rule WHERE games.id=$1;")?;
+    let res = client.query(&stmt, &[&form.game_id.to_string()])?;
+    ensure!(res.len() == 1, "no game found");
+    let rule: Json<Rule> = res[0].get(0);
+
+    // states saved in the same moment have the same `time`, so the last one is found by `number`
+    let stmt = client.prepare("SELECT state FROM records WHERE game_id=$1 ORDER BY number DESC LIMIT 1;")?;
+    let res = client.query(&stmt, &[&form.game_id.to_string()])?;
+    ensure!(res.len() == 1, "no state found");
+    let state: Json<State> = res[0].get(0);
+    Ok((rule.0, state.0))
+}
+
 #[derive(Deserialize, Serialize, Clone)]
 pub struct GetRuleForm {
     pub rule_hash: RuleHash,
@@ -122,7 +147,7 @@ pub struct GetRuleForm {
 
 pub fn get_rule(form: &GetRuleForm, pool: Pool) -> Result<Rule> {
     let mut client = pool.get()?;
-    let stmt = client.prepare("")?; // todo
+    let stmt = client.prepare("SELECT rule FROM rules WHERE rule_hash=$1;")?;
     let res = client.query(&stmt, &[&form.rule_hash.to_string()])?;
     ensure!(res.len() == 1, "no rule found");
     let rule: Json<Rule> = res[0].get(0);
@@ -136,8 +161,9 @@ pub struct SaveRuleForm {
 
 pub fn save_rule(form: &SaveRuleForm, pool: Pool) -> Result<()> {
     let mut client = pool.get()?;
-    let stmt = client.prepare("")?; // todo
-    let _ = client.query(&stmt, &[&Json(&form.rule)]);
+    let stmt =
+        client.prepare("INSERT INTO rules (rule_hash, rule) VALUES ($1, $2) ON CONFLICT (rule_hash) DO NOTHING;")?;
+    let _ = client.query(&stmt, &[&RuleHash::generate(&form.rule).to_string(), &Json(&form.rule)])?;
     Ok(())
 }
 
@@ -247,3 +273,71 @@ pub fn get_room_list(form: &GetRoomListForm, pool: Pool) -> Result<Vec<RoomId>>
     let res = client.query(&stmt, &[&form.user_num.0, &form.user_num.1])?;
     Ok(res.iter().map(|x| RoomId(x.get(0))).collect())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mighty::prelude::{Command, Phase, Preset};
+    use r2d2_postgres::postgres::NoTls;
+    use r2d2_postgres::PostgresConnectionManager;
+
+    /// Pool of the database given by `MIGHTY_TEST_DB`, such as `host=localhost user=postgres`
+    fn test_pool() -> Pool {
+        let config = std::env::var("MIGHTY_TEST_DB").expect("MIGHTY_TEST_DB is not set");
+        let pool = Pool::new(PostgresConnectionManager::new(config.parse().unwrap(), NoTls)).unwrap();
+        crate::db::init(pool.clone()).unwrap();
+        pool
+    }
+
+    #[test]
+    #[ignore = "needs postgresql at MIGHTY_TEST_DB"]
+    fn game_result_test() {
+        let pool = test_pool();
+        let rule = Rule::from(Preset::Default5);
+        let game_id = GameId::generate_random();
+        let room_id = RoomUid::generate_random();
+        let form = MakeGameForm {
+            game_id,
+            room_id,
+            room_name: "test".to_owned(),
+            users: vec![1, 2, 3, 4, 5],
+            is_rank: false,
+            rule: rule.clone(),
+        };
+        make_game(&form, pool.clone()).unwrap();
+
+        // states are saved right after each other, so that some of them have the same time
+        let mut state = State::new(&rule);
+        let mut number = 0;
+        while state.phase() != Phase::GameEnded {
+            save_state(
+                &SaveStateForm {
+                    game_id,
+                    room_id,
+                    number,
+                    state: state.clone(),
+                },
+                pool.clone(),
+            )
+            .unwrap();
+            let user = state.valid_users(&rule).trailing_zeros() as usize;
+            state = state.next(user, Command::Random, &rule).unwrap();
+            number += 1;
+        }
+        let form = SaveStateForm {
+            game_id,
+            room_id,
+            number,
+            state: state.clone(),
+        };
+        save_state(&form, pool.clone()).unwrap();
+
+        let (saved_rule, saved_state) = get_game_result(&GetGameResultForm { game_id }, pool.clone()).unwrap();
+        assert_eq!(saved_rule, rule);
+        assert_eq!(saved_state, state);
+        let form = GetGameResultForm {
+            game_id: GameId::generate_random(),
+        };
+        assert!(get_game_result(&form, pool).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+pub mod chat;
 pub mod game;
 pub mod user;
 
@@ -11,11 +12,17 @@ const CREATE_ROOM_SQL: &str = include_str!("./sql/create_room.sql");
 #[cfg(target_os = "windows")]
 const CREATE_ROOM_SQL: &str = include_str!(".\\sql\\create_room.sql");
 
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+const CREATE_CHAT_SQL: &str = include_str!("./sql/create_chat.sql");
+#[cfg(target_os = "windows")]
+const CREATE_CHAT_SQL: &str = include_str!(".\\sql\\create_chat.sql");
+
 use crate::dev::*;
 
 pub fn init(pool: Pool) -> Result<()> {
     let mut client = pool.get()?;
     client.simple_query(CREATE_USER_SQL)?;
     client.simple_query(CREATE_ROOM_SQL)?;
+    client.simple_query(CREATE_CHAT_SQL)?;
     Ok(())
 }
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS chats
+(
+    room_id    CHAR(64)      NOT NULL,
+    game_id    CHAR(64),                -- null if the chat is not in game
+    no         BIGINT        NOT NULL,  -- nth chat in room
+    channel    JSON          NOT NULL,  -- `ChatChannel`
+    user_no    INTEGER       NOT NULL,
+    content    VARCHAR(4095) NOT NULL,  -- filtered content
+    deleted    BOOLEAN       NOT NULL DEFAULT FALSE,
+    time       TIMESTAMP              DEFAULT now()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS chats_room_no_index ON chats (room_id, no);
+CREATE INDEX IF NOT EXISTS chats_game_id_index ON chats (game_id);
+CREATE INDEX IF NOT EXISTS chats_user_no_index ON chats (user_no);
@@ -5,3 +5,4 @@ DROP TABLE IF EXISTS games;
 DROP TABLE IF EXISTS records;
 DROP TABLE IF EXISTS ratings;
 DROP TABLE IF EXISTS rules;
+DROP TABLE IF EXISTS chats;
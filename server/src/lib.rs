@@ -19,10 +19,12 @@
 
 mod actor;
 mod app_state;
+mod chat;
 mod config;
 mod db;
 pub mod error;
 mod middlewares;
+mod rate_limit;
 mod service;
 
 /// # Constant module
@@ -46,6 +48,15 @@ mod constant {
 
     /// Token is valid during `TOKEN_VALID_DURATION`.
     pub const TOKEN_VALID_DURATION: Duration = Duration::from_secs(24 * 60 * 60);
+
+    /// After `MALFORMED_MESSAGE_LIMIT` messages that can't be parsed, websocket is disconnected.
+    pub const MALFORMED_MESSAGE_LIMIT: u32 = 10;
+
+    /// Game review visits at most `REVIEW_SEARCH_LIMIT` positions in total, and skips the positions over it.
+    pub const REVIEW_SEARCH_LIMIT: usize = 300_000;
+
+    /// Pledge advice plays `ADVICE_SAMPLES` sampled games for each giruda.
+    pub const ADVICE_SAMPLES: usize = 30;
 }
 
 /// # Dev module
@@ -124,7 +135,14 @@ pub mod internal {
         let private_key = conf.secret.clone();
         let redirect = conf.https.as_ref().unwrap().redirect;
 
-        let state = AppState::new(serve_path, pg_config, mail.clone(), hex::encode(&conf.secret));
+        let state = AppState::new(
+            serve_path,
+            pg_config,
+            mail.clone(),
+            hex::encode(&conf.secret),
+            conf.rate_limit.clone(),
+            conf.get_chat(),
+        );
         mail.do_send(SetAppState(state.clone()));
 
         HttpServer::new(move || {
@@ -158,7 +176,14 @@ pub mod internal {
         let pg_config = conf.get_pg_config();
         let private_key = conf.secret.clone();
 
-        let state = AppState::new(serve_path, pg_config, mail.clone(), hex::encode(&conf.secret));
+        let state = AppState::new(
+            serve_path,
+            pg_config,
+            mail.clone(),
+            hex::encode(&conf.secret),
+            conf.rate_limit.clone(),
+            conf.get_chat(),
+        );
         mail.do_send(SetAppState(state.clone()));
 
         HttpServer::new(move || {
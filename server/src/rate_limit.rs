@@ -0,0 +1,288 @@
+//! # Rate limit
+//!
+//! Token bucket to limit how fast client can send messages or requests.
+//! Each bucket holds at most `burst` tokens and refills `per_minute` tokens in a minute.
+//! One message or request takes one token, and it is rejected when there is no token left.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Configuration of one token bucket
+///
+/// - `burst`: maximum count of tokens (messages that can be sent at once)
+/// - `per_minute`: count of tokens refilled in a minute
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+pub struct Limit {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+impl Limit {
+    pub fn new(burst: u32, per_minute: u32) -> Limit {
+        Limit { burst, per_minute }
+    }
+}
+
+/// Kinds of websocket message which are limited separately
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum LimitKind {
+    Chat,
+    Command,
+    RoomList,
+    Advice,
+}
+
+/// Rate limit configuration for every kind of message & request
+///
+/// `trusted_proxies` are the proxies whose `X-Forwarded-For` header is used as the client address.
+/// The header from any other peer is ignored, since the client can write anything in it.
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    pub chat: Limit,
+    pub command: Limit,
+    pub room_list: Limit,
+    pub advice: Limit,
+    pub login: Limit,
+    pub pre_register: Limit,
+    pub regenerate_token: Limit,
+    pub review: Limit,
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        RateLimit {
+            chat: Limit::new(5, 30),
+            command: Limit::new(20, 240),
+            room_list: Limit::new(3, 20),
+            advice: Limit::new(2, 6),
+            login: Limit::new(5, 10),
+            pre_register: Limit::new(2, 4),
+            regenerate_token: Limit::new(2, 4),
+            review: Limit::new(2, 6),
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+impl RateLimit {
+    pub fn get(&self, kind: LimitKind) -> Limit {
+        match kind {
+            LimitKind::Chat => self.chat,
+            LimitKind::Command => self.command,
+            LimitKind::RoomList => self.room_list,
+            LimitKind::Advice => self.advice,
+        }
+    }
+}
+
+/// Token bucket
+#[derive(Debug, Clone)]
+pub struct Bucket {
+    limit: Limit,
+    tokens: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    pub fn new(limit: Limit) -> Bucket {
+        Bucket {
+            limit,
+            tokens: limit.burst as f64,
+            last: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limit.per_minute as f64 / 60.0).min(self.limit.burst as f64);
+        self.last = now;
+    }
+
+    /// Takes one token. Returns `false` if there is no token left.
+    pub fn take(&mut self) -> bool {
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// If the bucket is full, it is same as new bucket.
+    pub fn is_full(&mut self) -> bool {
+        self.refill();
+        self.tokens >= self.limit.burst as f64
+    }
+}
+
+/// Token buckets for each key (ex. ip address)
+///
+/// This is used in http services, so it is shared among threads.
+/// Full buckets are removed every `SWEEP_INTERVAL`.
+/// When a new key comes while there are `capacity` buckets, full buckets are removed,
+/// or the one with the most tokens if there is none, since it is the closest to a new bucket.
+/// A drained bucket is never removed before it refills, so new keys can't reset the limit of others.
+#[derive(Debug)]
+pub struct KeyedLimiter {
+    limit: Limit,
+    capacity: usize,
+    buckets: Mutex<(HashMap<String, Bucket>, Instant)>,
+}
+
+/// Default maximum count of buckets
+const MAX_BUCKETS: usize = 4096;
+
+/// Interval to remove full buckets
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+impl KeyedLimiter {
+    pub fn new(limit: Limit) -> KeyedLimiter {
+        KeyedLimiter::with_capacity(limit, MAX_BUCKETS)
+    }
+
+    pub fn with_capacity(limit: Limit, capacity: usize) -> KeyedLimiter {
+        KeyedLimiter {
+            limit,
+            capacity,
+            buckets: Mutex::new((HashMap::new(), Instant::now())),
+        }
+    }
+
+    /// Takes one token from the bucket of `key`. Returns `false` if there is no token left.
+    pub fn take<S: AsRef<str>>(&self, key: S) -> bool {
+        let mut guard = self.buckets.lock().unwrap();
+        let (buckets, last_sweep) = &mut *guard;
+        if last_sweep.elapsed() >= SWEEP_INTERVAL {
+            buckets.retain(|_, b| !b.is_full());
+            *last_sweep = Instant::now();
+        }
+        if let Some(bucket) = buckets.get_mut(key.as_ref()) {
+            return bucket.take();
+        }
+        if buckets.len() >= self.capacity {
+            buckets.retain(|_, b| !b.is_full());
+            *last_sweep = Instant::now();
+        }
+        if buckets.len() >= self.capacity {
+            let fullest = buckets
+                .iter_mut()
+                .map(|(k, b)| {
+                    b.refill();
+                    (k, b.tokens)
+                })
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(k, _)| k.clone());
+            if let Some(fullest) = fullest {
+                buckets.remove(&fullest);
+            }
+        }
+        let mut bucket = Bucket::new(self.limit);
+        let res = bucket.take();
+        buckets.insert(key.as_ref().to_owned(), bucket);
+        res
+    }
+}
+
+/// Limiters for http requests
+#[derive(Debug)]
+pub struct HttpLimiter {
+    pub login: KeyedLimiter,
+    pub pre_register: KeyedLimiter,
+    pub regenerate_token: KeyedLimiter,
+    pub review: KeyedLimiter,
+    trusted_proxies: Vec<IpAddr>,
+}
+
+impl HttpLimiter {
+    pub fn new(rate_limit: &RateLimit) -> HttpLimiter {
+        HttpLimiter {
+            login: KeyedLimiter::new(rate_limit.login),
+            pre_register: KeyedLimiter::new(rate_limit.pre_register),
+            regenerate_token: KeyedLimiter::new(rate_limit.regenerate_token),
+            review: KeyedLimiter::new(rate_limit.review),
+            trusted_proxies: rate_limit.trusted_proxies.clone(),
+        }
+    }
+
+    /// Ip address of the client, to use as key of rate limit
+    ///
+    /// `X-Forwarded-For` is read only when the peer is a trusted proxy,
+    /// and the last address in it which is not a trusted proxy is the client.
+    pub fn client_ip(&self, peer: Option<IpAddr>, forwarded_for: Option<&str>) -> String {
+        let peer = match peer {
+            Some(peer) => peer,
+            None => return "unknown".to_owned(),
+        };
+        if !self.trusted_proxies.contains(&peer) {
+            return peer.to_string();
+        }
+        forwarded_for
+            .into_iter()
+            .flat_map(|h| h.rsplit(','))
+            .map(|addr| addr.trim().parse::<IpAddr>())
+            .take_while(Result::is_ok)
+            .flatten()
+            .find(|addr| !self.trusted_proxies.contains(addr))
+            .unwrap_or(peer)
+            .to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bucket_test() {
+        let mut bucket = Bucket::new(Limit::new(3, 0));
+        assert!(bucket.take());
+        assert!(bucket.take());
+        assert!(bucket.take());
+        assert!(!bucket.take());
+    }
+
+    #[test]
+    fn keyed_limiter_test() {
+        let limiter = KeyedLimiter::new(Limit::new(1, 0));
+        assert!(limiter.take("a"));
+        assert!(!limiter.take("a"));
+        assert!(limiter.take("b"));
+    }
+
+    #[test]
+    fn keyed_limiter_capacity_test() {
+        let limiter = KeyedLimiter::with_capacity(Limit::new(2, 0), 2);
+        assert!(limiter.take("a"));
+        assert!(limiter.take("a"));
+        assert!(limiter.take("b"));
+        // `b` has more tokens than `a`, so it is removed for `c`
+        assert!(limiter.take("c"));
+        assert!(!limiter.take("a"));
+        for key in 0..10 {
+            assert!(limiter.take(key.to_string()));
+        }
+        assert!(!limiter.take("a"));
+        assert_eq!(limiter.buckets.lock().unwrap().0.len(), 2);
+    }
+
+    #[test]
+    fn client_ip_test() {
+        let proxy = "10.0.0.1".parse().unwrap();
+        let client = "1.2.3.4".parse().unwrap();
+        let limiter = HttpLimiter::new(&RateLimit {
+            trusted_proxies: vec![proxy],
+            ..RateLimit::default()
+        });
+        assert_eq!(limiter.client_ip(Some(client), Some("5.6.7.8")), "1.2.3.4");
+        assert_eq!(limiter.client_ip(Some(proxy), Some("5.6.7.8, 1.2.3.4")), "1.2.3.4");
+        assert_eq!(limiter.client_ip(Some(proxy), Some("1.2.3.4, 10.0.0.1")), "1.2.3.4");
+        assert_eq!(limiter.client_ip(Some(proxy), Some("garbage")), "10.0.0.1");
+        assert_eq!(limiter.client_ip(Some(proxy), None), "10.0.0.1");
+    }
+}
@@ -3,13 +3,18 @@ pub mod ws;
 
 use crate::actor::mail::SendVerification;
 use crate::app_state::AppState;
+use crate::db::game::{get_game_result, get_rule, GetGameResultForm, GetRuleForm};
 use crate::db::user::{get_user_info, GetInfoForm};
 use crate::dev::*;
 use crate::service::p404;
 use actix_identity::Identity;
+use actix_web::error::BlockingError;
 use actix_web::http::header;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
 use actix_web::{get, web, HttpResponse, Responder};
 use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use mighty::prelude::{Format, Review};
+use serde::Deserialize;
 use serde_json::{json, Map};
 
 #[get("/admin")]
@@ -184,6 +189,68 @@ pub async fn resource(state: web::Data<AppState>, web::Path(file): web::Path<Str
     }
 }
 
+/// Format of the rule file, toml by default
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuleFileQuery {
+    #[serde(default)]
+    pub format: Format,
+}
+
+#[get("/rule/{rule_hash}")]
+pub async fn rule_file(
+    state: web::Data<AppState>,
+    web::Path(rule_hash): web::Path<String>,
+    query: web::Query<RuleFileQuery>,
+) -> Result<HttpResponse, Error> {
+    let rule_hash: RuleHash = rule_hash
+        .parse()
+        .map_err(|_| err!(StatusCode::BAD_REQUEST, "invalid rule hash"))?;
+    let rule = get_rule(&GetRuleForm { rule_hash }, state.pool.clone())
+        .map_err(|_| err!(StatusCode::NOT_FOUND, "no rule found"))?;
+    let body = rule.export(query.format)?;
+    Ok(HttpResponse::Ok()
+        .set(header::ContentType(query.format.mime().parse().unwrap()))
+        .set(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!(
+                "{}.{}",
+                rule_hash,
+                query.format.extension()
+            ))],
+        })
+        .body(body))
+}
+
+/// Double-dummy review of the finished game, see `Review::analyze`
+#[get("/review/{game_id}")]
+pub async fn review(
+    id: Identity,
+    state: web::Data<AppState>,
+    web::Path(game_id): web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    let user_no = id
+        .identity()
+        .ok_or_else(|| err!(StatusCode::UNAUTHORIZED, "login required"))?;
+    ensure!(
+        state.limiter.review.take(user_no),
+        StatusCode::TOO_MANY_REQUESTS,
+        "too many review requests"
+    );
+    let game_id: GameId = game_id
+        .parse()
+        .map_err(|_| err!(StatusCode::BAD_REQUEST, "invalid game id"))?;
+    let (rule, game) = get_game_result(&GetGameResultForm { game_id }, state.pool.clone())
+        .map_err(|_| err!(StatusCode::NOT_FOUND, "no game found"))?;
+    // search takes a while, so it runs on the thread pool
+    let review = web::block(move || Review::analyze(&rule, &game, REVIEW_SEARCH_LIMIT))
+        .await
+        .map_err(|e| match e {
+            BlockingError::Error(e) => err!(StatusCode::BAD_REQUEST, "{}", e),
+            BlockingError::Canceled => err!(StatusCode::INTERNAL_SERVER_ERROR, "review canceled"),
+        })?;
+    Ok(HttpResponse::Ok().json(review))
+}
+
 #[get("/room/{room_id}")]
 pub async fn room(id: Identity, state: web::Data<AppState>, web::Path(room_id): web::Path<String>) -> impl Responder {
     if let Some(id) = id.identity() {
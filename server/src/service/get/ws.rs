@@ -2,6 +2,7 @@ use crate::actor::hub::{GetRoom, HubConnect};
 use crate::actor::session::SessionTrait;
 use crate::actor::{List, Main, Observe, RoomUser};
 use crate::app_state::AppState;
+use crate::db::user::{get_user_info, GetInfoForm};
 use crate::dev::*;
 use crate::service::p404;
 use actix_identity::Identity;
@@ -17,7 +18,11 @@ pub async fn list(
     stream: web::Payload,
 ) -> Result<HttpResponse, Error> {
     if id.identity().is_some() {
-        ws::start(List::new(state.hub.clone(), state.pool.clone()).make(), &req, stream)
+        ws::start(
+            List::new(state.hub.clone(), state.pool.clone()).make(state.rate_limit.clone()),
+            &req,
+            stream,
+        )
     } else {
         Ok(p404(state).await)
     }
@@ -38,7 +43,11 @@ pub async fn main(
             .into_future()
             .await
             .unwrap()?;
-        ws::start(Main::new(addr, state.hub.clone()).make(), &req, stream)
+        ws::start(
+            Main::new(UserNo(user_no), addr, state.hub.clone()).make(state.rate_limit.clone()),
+            &req,
+            stream,
+        )
     } else {
         Ok(p404(state).await)
     }
@@ -54,9 +63,17 @@ pub async fn observe(
 ) -> Result<HttpResponse, Error> {
     if let Some(id) = id.identity() {
         let user_no = id.parse().unwrap();
-        let room_id = room_id.parse::<u32>().unwrap().into();
+        let room_id = match room_id.parse::<u32>() {
+            Ok(room_id) => room_id.into(),
+            Err(_) => return Ok(p404(state).await),
+        };
         let addr = state.hub.send(GetRoom(room_id)).into_future().await.unwrap()?;
-        ws::start(Observe::new(addr, UserNo(user_no)).make(), &req, stream)
+        let info = get_user_info(&GetInfoForm::UserNo(user_no), state.pool.clone())?;
+        ws::start(
+            Observe::new(addr, UserNo(user_no), info.is_admin).make(state.rate_limit.clone()),
+            &req,
+            stream,
+        )
     } else {
         Ok(p404(state).await)
     }
@@ -77,7 +94,7 @@ pub async fn room(
             .into_future()
             .await
             .unwrap()?;
-        ws::start(RoomUser::new(addr).make(), &req, stream)
+        ws::start(RoomUser::new(addr).make(state.rate_limit.clone()), &req, stream)
     } else {
         Ok(p404(state).await)
     }
@@ -20,7 +20,9 @@ pub fn config_services(cfg: &mut web::ServiceConfig) {
         .service(get::register)
         .service(get::register_complete)
         .service(get::resource)
+        .service(get::review)
         .service(get::room)
+        .service(get::rule_file)
         // .service(get::setting)
         // .service(get::user_info)
         .service(
@@ -34,6 +36,7 @@ pub fn config_services(cfg: &mut web::ServiceConfig) {
         .service(post::regenerate_token)
         .service(post::pre_register)
         .service(post::register)
+        .service(post::upload_rule)
         .service(post::validate_email)
         .service(post::validate_user_id)
         .service(delete::delete_user);
@@ -1,27 +1,52 @@
 use crate::app_state::AppState;
+use crate::db::game::{save_rule, SaveRuleForm};
 use crate::db::user::{
     check_user_email, check_user_id, login_user, pre_register_user, regenerate_user_token, register_user,
     CheckEmailForm, CheckIdForm, LoginForm, PreRegisterForm, RegenerateTokenForm, RegisterForm,
 };
 use crate::dev::*;
+use crate::service::get::RuleFileQuery;
 use actix_identity::Identity;
 use actix_web::http::header;
-use actix_web::{post, web, HttpResponse};
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use mighty::prelude::Rule;
 use serde::Serialize;
 
+/// Ip address of client to use as key of rate limit
+fn client_ip(req: &HttpRequest, state: &AppState) -> String {
+    let forwarded_for = req.headers().get("x-forwarded-for").and_then(|h| h.to_str().ok());
+    state.limiter.client_ip(req.peer_addr().map(|a| a.ip()), forwarded_for)
+}
+
 #[post("/login")]
 pub async fn login(
     id: Identity,
+    req: HttpRequest,
     form: web::Json<LoginForm>,
     state: web::Data<AppState>,
 ) -> Result<HttpResponse, Error> {
+    ensure!(
+        state.limiter.login.take(client_ip(&req, &state)),
+        StatusCode::TOO_MANY_REQUESTS,
+        "too many login requests"
+    );
     let user_no = login_user(&*form, state.pool.clone())?;
     id.remember(user_no.to_string());
     Ok(HttpResponse::Ok().finish())
 }
 
 #[post("/pre-register")]
-pub async fn pre_register(form: web::Json<PreRegisterForm>, state: web::Data<AppState>) -> Result<HttpResponse, Error> {
+pub async fn pre_register(
+    req: HttpRequest,
+    form: web::Json<PreRegisterForm>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    ensure!(
+        state.limiter.pre_register.take(client_ip(&req, &state))
+            && state.limiter.pre_register.take(format!("email:{}", form.email)),
+        StatusCode::TOO_MANY_REQUESTS,
+        "too many pre-register requests"
+    );
     let form = pre_register_user(&*form, state.pool.clone())?;
     state.mail.do_send(form);
     Ok(HttpResponse::Ok().finish())
@@ -29,9 +54,16 @@ pub async fn pre_register(form: web::Json<PreRegisterForm>, state: web::Data<App
 
 #[post("/regenerate-token")]
 pub async fn regenerate_token(
+    req: HttpRequest,
     form: web::Path<RegenerateTokenForm>,
     state: web::Data<AppState>,
 ) -> Result<HttpResponse, Error> {
+    ensure!(
+        state.limiter.regenerate_token.take(client_ip(&req, &state))
+            && state.limiter.regenerate_token.take(format!("email:{}", form.email)),
+        StatusCode::TOO_MANY_REQUESTS,
+        "too many token regeneration requests"
+    );
     let form = regenerate_user_token(&*form, state.pool.clone())?;
     state.mail.do_send(form);
     Ok(HttpResponse::Found().header(header::LOCATION, "/").finish())
@@ -86,3 +118,32 @@ pub async fn validate_email(
         .unwrap(),
     ))
 }
+
+#[derive(Debug, Clone, Serialize)]
+struct UploadRuleResult {
+    rule_hash: RuleHash,
+}
+
+/// Uploads the rule file, and returns the hash of the rule.
+/// Rejects with every violated constraint if the rule is invalid.
+#[post("/rule")]
+pub async fn upload_rule(
+    id: Identity,
+    query: web::Query<RuleFileQuery>,
+    body: String,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    ensure!(id.identity().is_some(), StatusCode::UNAUTHORIZED, "login required");
+    let rule = Rule::import(&body, query.format).map_err(|e| err!(StatusCode::BAD_REQUEST, e))?;
+    if let Err(errors) = rule.validate() {
+        let errors = errors.iter().map(RuleViolation::from).collect::<Vec<_>>();
+        return Ok(HttpResponse::BadRequest().body(serde_json::to_string(&errors).unwrap()));
+    }
+    save_rule(&SaveRuleForm { rule: rule.clone() }, state.pool.clone())?;
+    Ok(HttpResponse::Ok().body(
+        serde_json::to_string(&UploadRuleResult {
+            rule_hash: RuleHash::generate(&rule),
+        })
+        .unwrap(),
+    ))
+}
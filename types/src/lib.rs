@@ -1,7 +1,8 @@
 #![cfg(not(tarpaulin_include))]
 
 use bitflags::bitflags;
-use mighty::prelude::{Command, Rule, State};
+use mighty::error::RuleError;
+use mighty::prelude::{Advice, Command, Rule, State};
 use serde::{Deserialize, Serialize};
 use std::fmt::{self, Display};
 #[cfg(feature = "client")]
@@ -266,6 +267,23 @@ impl RuleHash {
     }
 }
 
+/// Mode of spectating, which decides how observers receive the game state
+///
+/// - `Live`: Same state as users, immediately
+/// - `DelayMoves`: Each state is sent after the number of moves are made
+/// - `DelaySeconds`: Each state is sent after the number of seconds
+/// - `HideHands`: State is sent immediately, but without cards in hands
+/// - `AfterGame`: Nothing is sent during the game, and every state is sent after the game ends
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize, Default)]
+pub enum SpectateMode {
+    #[default]
+    Live,
+    DelayMoves(u32),
+    DelaySeconds(u32),
+    HideHands,
+    AfterGame,
+}
+
 /// Information of room
 ///
 /// - `uid`: uid of room
@@ -277,6 +295,8 @@ impl RuleHash {
 /// - `user`: user list who plays game
 /// - `observer`: observer list
 /// - `is_game`: if room is on gaming
+/// - `spectate`: spectating mode for observers
+/// - `allow_undo`: if users can take back their last command, always false in rank room
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "server", derive(Message, MessageResponse))]
 #[cfg_attr(feature = "server", rtype(result = "()"))]
@@ -290,6 +310,10 @@ pub struct RoomInfo {
     pub user: Vec<UserNo>,
     pub observer_cnt: usize,
     pub is_game: bool,
+    #[serde(default)]
+    pub spectate: SpectateMode,
+    #[serde(default)]
+    pub allow_undo: bool,
 }
 
 /// Simplified information of room for in the list
@@ -366,6 +390,48 @@ bitflags! {
     }
 }
 
+/// Channel of chat in the room
+///
+/// - `Room`: Seen by everyone in the room. Observers can't send during the game.
+/// - `Table`: Seen only by users who play the game
+/// - `Spectator`: Seen only by observers
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum ChatChannel {
+    Room,
+    Table,
+    Spectator,
+}
+
+/// Chat message in the room
+///
+/// - `id`: id of chat, unique in the room
+/// - `channel`: channel of chat
+/// - `user`: user who sent the chat
+/// - `content`: content of chat (already filtered by server)
+/// - `time`: time when the chat is sent (unix time in milliseconds)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub id: u64,
+    pub channel: ChatChannel,
+    pub user: UserNo,
+    pub content: String,
+    pub time: u64,
+}
+
+/// Direct message between online users
+///
+/// - `from`: user who sent the message
+/// - `to`: user who receives the message
+/// - `content`: content of message (already filtered by server)
+/// - `time`: time when the message is sent (unix time in milliseconds)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectMessage {
+    pub from: UserNo,
+    pub to: UserNo,
+    pub content: String,
+    pub time: u64,
+}
+
 /// Websocket message for room listing to client
 ///
 /// - `Room`: Sends the info of room
@@ -392,12 +458,16 @@ pub enum ListToServer {
 ///
 /// - `UserStatus`: Sends the status of user
 /// - `UserInfo`: Sends the information of user
+/// - `DirectMessage`: Direct message sent or received by user
+/// - `Error`: When the message from client is rejected
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[cfg_attr(feature = "server", derive(Message))]
 #[cfg_attr(feature = "server", rtype(result = "()"))]
 pub enum MainToClient {
     UserStatus(UserNo, UserStatus),
     UserInfo(UserInfo),
+    DirectMessage(DirectMessage),
+    Error { code: String, message: String },
 }
 
 /// Websocket message for main connection to server
@@ -406,47 +476,84 @@ pub enum MainToClient {
 /// - `Unsubscribe`: Unsubscribe for changes of user state
 /// - `GetInfo`: Request user info
 /// - `Update`: Check for user movement
+/// - `DirectMessage`: Sends direct message to online user
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum MainToServer {
     Subscribe(UserNo),
     Unsubscribe(UserNo),
     Update,
+    DirectMessage(UserNo, String),
 }
 
 /// Websocket message for observer connection to client
 ///
 /// - `Room`: Information of room
 /// - `Game`: Information of game
-/// - `Chat`: For receiving chats
+/// - `Chat`: For receiving chats (recent chats are sent again right after joining)
+/// - `DeleteChat`: When the chat with the id is deleted by admin
+/// - `Error`: When the message from client is rejected
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[cfg_attr(feature = "server", derive(Message))]
 #[cfg_attr(feature = "server", rtype(result = "()"))]
 pub enum ObserveToClient {
     Room(RoomInfo),
     Game(State),
-    Chat(String, UserNo),
+    Chat(ChatMessage),
+    DeleteChat(u64),
+    Error { code: String, message: String },
 }
 
 /// Websocket message for observer connection to server
 ///
-/// - `Chat`: When observer chat
+/// - `Chat`: When observer chat (to `Room` or `Spectator` channel)
+/// - `DeleteChat`: Deletes the chat (admin only)
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum ObserveToServer {
-    Chat(String),
+    Chat(ChatChannel, String),
+    DeleteChat(u64),
+}
+
+/// Violated constraint of the rule
+///
+/// - `code`: from `mighty::error::RuleError::code`
+/// - `message`: human readable message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleViolation {
+    pub code: String,
+    pub message: String,
+}
+
+impl From<&RuleError> for RuleViolation {
+    fn from(e: &RuleError) -> Self {
+        RuleViolation {
+            code: e.code().to_owned(),
+            message: e.to_string(),
+        }
+    }
 }
 
 /// Websocket message for room connection to client
 ///
 /// - `Room`: Information of room
 /// - `Game`: Information of game
-/// - `Chat`: For receiving chats
+/// - `Chat`: For receiving chats (recent chats are sent again right after joining)
+/// - `DeleteChat`: When the chat with the id is deleted by admin
+/// - `Error`: When the message from client is rejected (`code` is from `mighty::error::Error::code`)
+/// - `InvalidRule`: When the changed rule is rejected, with every violated constraint
+/// - `UndoRequest`: When the user requests to take back the last command, `None` when the request is closed
+/// - `Advice`: Estimated results of the pledges which the user can make, only for the user who requested it
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[cfg_attr(feature = "server", derive(Message))]
 #[cfg_attr(feature = "server", rtype(result = "()"))]
 pub enum RoomUserToClient {
     Room(RoomInfo),
     Game(State),
-    Chat(String, UserNo),
+    Chat(ChatMessage),
+    DeleteChat(u64),
+    Error { code: String, message: String },
+    InvalidRule(Vec<RuleViolation>),
+    UndoRequest(Option<UserNo>),
+    Advice(Vec<Advice>),
 }
 
 /// Websocket message for room connection to server
@@ -454,12 +561,29 @@ pub enum RoomUserToClient {
 /// - `Start`: Starts the game
 /// - `ChangeName`: Change the name of the room
 /// - `ChangeRule`: Change the rule of the room
+/// - `ChangeSpectateMode`: Change the spectating mode of the room
+/// - `ChangeAllowUndo`: Allow or disallow taking back the last command (not in rank room)
 /// - `Command`: Command for next move
+/// - `RequestUndo`: Requests to take back the last command, before the next user plays
+/// - `AnswerUndo`: Accepts or rejects the request (all other users or the head should accept)
+/// - `RequestAdvice`: Requests the estimated results of the pledges during the election (not in rank room)
+/// - `Chat`: Sends chat (to `Room` or `Table` channel)
+/// - `Mute`: Mutes the user in the room (head or admin only)
+/// - `Unmute`: Unmutes the user in the room (head or admin only)
+/// - `DeleteChat`: Deletes the chat (admin only)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RoomUserToServer {
     Start,
     ChangeName(String),
     ChangeRule(Rule),
+    ChangeSpectateMode(SpectateMode),
+    ChangeAllowUndo(bool),
     Command(Command),
-    Chat(String),
+    RequestUndo,
+    AnswerUndo(bool),
+    RequestAdvice,
+    Chat(ChatChannel, String),
+    Mute(UserNo),
+    Unmute(UserNo),
+    DeleteChat(u64),
 }